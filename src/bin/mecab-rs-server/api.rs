@@ -0,0 +1,278 @@
+use std::io::{Cursor, Read};
+use std::net::SocketAddr;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use mecab::{LatticeGraph, TaggerPool};
+use serde::Deserialize;
+use serde_json::{self, json, Value};
+use tiny_http::{Header, Method, Request, Response};
+
+pub type JsonResponse = Response<Cursor<Vec<u8>>>;
+
+/// Shared by every worker, the pool is set once the model has loaded.
+pub struct State {
+    pool: OnceLock<Result<Arc<TaggerPool>, String>>,
+    max_body_size: usize,
+    timeout: Duration,
+    max_nbest: usize,
+    // threads reading a request body
+    readers: Arc<AtomicUsize>,
+    max_readers: usize,
+}
+
+impl State {
+    pub fn new(max_body_size: usize, timeout: Duration, max_nbest: usize, max_readers: usize) -> State {
+        State {
+            pool: OnceLock::new(),
+            max_body_size,
+            timeout,
+            max_nbest,
+            readers: Arc::new(AtomicUsize::new(0)),
+            max_readers,
+        }
+    }
+
+    pub fn set_pool(&self, pool: Result<Arc<TaggerPool>, String>) {
+        let _ = self.pool.set(pool);
+    }
+
+    fn reader(&self) -> Option<Reader> {
+        self.readers
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| if n < self.max_readers { Some(n + 1) } else { None })
+            .ok()
+            .map(|_| Reader(self.readers.clone()))
+    }
+}
+
+/// Counts a body reader thread until it is dropped.
+struct Reader(Arc<AtomicUsize>);
+
+impl Drop for Reader {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[derive(Deserialize)]
+struct TextRequest {
+    text: String,
+    #[serde(default)]
+    n: Option<usize>,
+}
+
+struct Error(u16, String);
+
+type ApiResult = Result<Value, Error>;
+
+/// Answers a request, POST bodies that are not received within the timeout are answered with 408
+/// and with 503 while too many other bodies are being read.
+pub fn handle(state: &State, request: Request) {
+    let started = Instant::now();
+    let (request, body) = if *request.method() == Method::Post {
+        match receive(state, request) {
+            Some((request, body)) => (request, Some(body)),
+            // already answered by `receive` or the thread reading the body
+            None => return,
+        }
+    } else {
+        (request, None)
+    };
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| route(state, &request, body, started)))
+        .unwrap_or_else(|_| Err(Error(500, "internal error".to_owned())));
+    let _ = request.respond(response(result));
+}
+
+fn response(result: ApiResult) -> JsonResponse {
+    match result {
+        Ok(body) => respond(200, &body),
+        Err(Error(status, message)) => respond(status, &json!({ "error": message })),
+    }
+}
+
+fn respond(status: u16, body: &Value) -> JsonResponse {
+    let header = Header::from_bytes(&b"Content-Type"[..], &b"application/json; charset=utf-8"[..]).unwrap();
+    Response::from_string(body.to_string()).with_status_code(status).with_header(header)
+}
+
+fn route(state: &State, request: &Request, body: Option<Result<Vec<u8>, Error>>, started: Instant) -> ApiResult {
+    let path = request.url().split('?').next().unwrap_or("").to_owned();
+    let method = request.method().clone();
+
+    match (path.as_str(), method) {
+        ("/health", Method::Get) => Ok(json!({ "status": "ok" })),
+        ("/ready", Method::Get) => {
+            match state.pool.get() {
+                Some(Ok(_)) => Ok(json!({ "status": "ready" })),
+                Some(Err(e)) => Err(Error(503, format!("model failed to load: {}", e))),
+                None => Err(Error(503, "model is loading".to_owned())),
+            }
+        }
+        ("/dictionary-info", Method::Get) => {
+            let pool = pool(state)?;
+            let dictionaries: Vec<Value> = pool.model()
+                .dictionary_info()
+                .iter()
+                .map(|dict| {
+                    json!({
+                        "filename": dict.filename,
+                        "charset": dict.charset,
+                        "size": dict.size,
+                        "type": dict.dict_type,
+                        "lsize": dict.lsize,
+                        "rsize": dict.rsize,
+                        "version": dict.version,
+                    })
+                })
+                .collect();
+            Ok(json!({ "dictionaries": dictionaries }))
+        }
+        ("/tokenize", Method::Post) => {
+            let body = parse_body(body)?;
+            let pool = pool(state)?;
+            let tokens = pool.parse_to_tokens(body.text.as_str())
+                .ok_or_else(|| Error(500, "parsing failed".to_owned()))?;
+            check_deadline(state, started)?;
+            Ok(json!({ "sentence": body.text, "tokens": tokens }))
+        }
+        ("/nbest", Method::Post) => {
+            let body = parse_body(body)?;
+            let n = body.n.unwrap_or(1);
+            if n == 0 || n > state.max_nbest {
+                return Err(Error(400, format!("n must be between 1 and {}", state.max_nbest)));
+            }
+
+            let pool = pool(state)?;
+            let mut tagger = pool.get();
+            tagger.lattice_mut().set_sentence(body.text.as_str());
+            let mut paths = Vec::new();
+            for path in tagger.nbest(n) {
+                check_deadline(state, started)?;
+                paths.push(path);
+            }
+            if paths.is_empty() {
+                return Err(Error(500, tagger.lattice().what()));
+            }
+            Ok(json!({ "sentence": body.text, "paths": paths }))
+        }
+        ("/lattice", Method::Post) => {
+            let body = parse_body(body)?;
+            let pool = pool(state)?;
+            let mut tagger = pool.get();
+            tagger.lattice_mut().set_sentence(body.text.as_str());
+            if !tagger.parse(tagger.lattice()) {
+                return Err(Error(500, tagger.lattice().what()));
+            }
+            let graph = LatticeGraph::new(tagger.lattice(), pool.model());
+            check_deadline(state, started)?;
+            serde_json::to_value(&graph).map_err(|e| Error(500, e.to_string()))
+        }
+        ("/health", _) | ("/ready", _) | ("/dictionary-info", _) | ("/tokenize", _) | ("/nbest", _) |
+        ("/lattice", _) => Err(Error(405, "method not allowed".to_owned())),
+        _ => Err(Error(404, "not found".to_owned())),
+    }
+}
+
+fn pool(state: &State) -> Result<&TaggerPool, Error> {
+    match state.pool.get() {
+        Some(Ok(pool)) => Ok(pool),
+        _ => Err(Error(503, "model is not ready".to_owned())),
+    }
+}
+
+// tiny_http has no socket timeouts, so the body is read on its own thread. If that takes
+// longer than the timeout the client stops being read and the thread answers with 408.
+fn receive(state: &State, mut request: Request) -> Option<(Request, Result<Vec<u8>, Error>)> {
+    let reader = match state.reader() {
+        Some(reader) => reader,
+        None => {
+            let _ = request.respond(respond(503, &json!({ "error": "too many request bodies are being read" })));
+            return None;
+        }
+    };
+
+    let (sender, receiver) = mpsc::channel();
+    let max_body_size = state.max_body_size;
+    let peer = request.remote_addr().cloned();
+    thread::spawn(move || {
+        let body = read_body(max_body_size, &mut request);
+        drop(reader);
+        if let Err(mpsc::SendError((request, _))) = sender.send((request, body)) {
+            let _ = request.respond(respond(408, &json!({ "error": "timed out reading the request body" })));
+        }
+    });
+
+    let received = receiver.recv_timeout(state.timeout).ok();
+    if received.is_none() {
+        if let Some(peer) = peer {
+            stop_reading(peer);
+        }
+    }
+    received
+}
+
+// tiny_http does not expose the socket of a request, so it is looked up by the client address.
+// Shutting down its reading half ends the blocked read of the body and keeps the connection
+// open for the 408 response.
+#[cfg(unix)]
+fn stop_reading(peer: SocketAddr) {
+    use std::fs;
+    use std::mem::ManuallyDrop;
+    use std::net::{Shutdown, TcpStream};
+    use std::os::unix::io::{FromRawFd, RawFd};
+
+    let fds: Vec<RawFd> = match fs::read_dir("/dev/fd") {
+        Ok(entries) => entries.filter_map(|e| e.ok()?.file_name().to_str()?.parse().ok()).collect(),
+        Err(_) => return,
+    };
+    for fd in fds {
+        // borrowed from tiny_http, which still owns and closes the socket
+        let stream = ManuallyDrop::new(unsafe { TcpStream::from_raw_fd(fd) });
+        if stream.peer_addr().ok() == Some(peer) {
+            let _ = stream.shutdown(Shutdown::Read);
+        }
+    }
+}
+
+// elsewhere the thread ends once the client sends the rest of the body or disconnects
+#[cfg(not(unix))]
+fn stop_reading(_: SocketAddr) {}
+
+fn read_body(max_body_size: usize, request: &mut Request) -> Result<Vec<u8>, Error> {
+    let too_large = || Error(413, format!("request body exceeds {} bytes", max_body_size));
+    if request.body_length().is_some_and(|len| len > max_body_size) {
+        return Err(too_large());
+    }
+
+    let mut body = Vec::new();
+    request.as_reader()
+        .take(max_body_size as u64 + 1)
+        .read_to_end(&mut body)
+        .map_err(|e| Error(400, e.to_string()))?;
+    if body.len() > max_body_size {
+        return Err(too_large());
+    }
+    Ok(body)
+}
+
+fn parse_body(body: Option<Result<Vec<u8>, Error>>) -> Result<TextRequest, Error> {
+    let body = body.unwrap_or_else(|| Ok(Vec::new()))?;
+    let body: TextRequest = serde_json::from_slice(&body).map_err(|e| Error(400, e.to_string()))?;
+    if body.text.contains('\0') {
+        return Err(Error(400, "text must not contain NUL".to_owned()));
+    }
+    Ok(body)
+}
+
+// libmecab cannot be interrupted, so the deadline is checked between the steps of a request
+fn check_deadline(state: &State, started: Instant) -> Result<(), Error> {
+    if started.elapsed() > state.timeout {
+        Err(Error(503, "request timed out".to_owned()))
+    } else {
+        Ok(())
+    }
+}
@@ -0,0 +1,142 @@
+extern crate mecab;
+extern crate serde;
+extern crate serde_json;
+extern crate tiny_http;
+
+mod api;
+
+use std::env;
+use std::process;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use mecab::{Model, Tagger, TaggerPool};
+use tiny_http::Server;
+
+use api::State;
+
+const USAGE: &str = "Usage: mecab-rs-server [options] [mecab options]
+
+Options:
+  --listen=ADDR          listen on ADDR, 127.0.0.1:8080 by default
+  --workers=INT          number of worker threads, the number of CPUs by default
+  --max-body-size=INT    reject request bodies larger than INT bytes, 1048576 by default
+  --timeout=INT          answer requests that take longer than INT milliseconds with 503, 5000 by default
+  --max-nbest=INT        largest N accepted by /nbest, 64 by default
+  --max-readers=INT      read at most INT request bodies at once and answer others with 503, 64 by default
+  -h, --help             show this help and exit
+
+Other options such as -d and -u are passed on to libmecab.
+";
+
+struct Options {
+    listen: String,
+    workers: usize,
+    max_body_size: usize,
+    timeout: Duration,
+    max_nbest: usize,
+    max_readers: usize,
+    mecab_args: Vec<String>,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut options = Options {
+        listen: "127.0.0.1:8080".to_owned(),
+        workers: thread::available_parallelism().map(|n| n.get()).unwrap_or(4),
+        max_body_size: 1 << 20,
+        timeout: Duration::from_millis(5000),
+        max_nbest: 64,
+        max_readers: 64,
+        mecab_args: Vec::new(),
+    };
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "-h" || arg == "--help" {
+            print!("{}", USAGE);
+            process::exit(0);
+        }
+
+        let (name, inline) = match arg.find('=') {
+            Some(i) if arg.starts_with("--") => (arg[..i].to_owned(), Some(arg[i + 1..].to_owned())),
+            _ => (arg.clone(), None),
+        };
+        let names = ["--listen", "--workers", "--max-body-size", "--timeout", "--max-nbest", "--max-readers"];
+        if !names.contains(&name.as_str()) {
+            options.mecab_args.push(arg);
+            continue;
+        }
+
+        let value = match inline {
+            Some(value) => value,
+            None => args.next().ok_or_else(|| format!("`{}` requires an argument", name))?,
+        };
+        let number = || value.parse::<usize>().map_err(|_| format!("invalid value `{}` for `{}`", value, name));
+        match name.as_str() {
+            "--listen" => options.listen = value.clone(),
+            "--workers" => options.workers = number()?.max(1),
+            "--max-body-size" => options.max_body_size = number()?,
+            "--timeout" => options.timeout = Duration::from_millis(number()? as u64),
+            "--max-nbest" => options.max_nbest = number()?.max(1),
+            _ => options.max_readers = number()?.max(1),
+        }
+    }
+
+    Ok(options)
+}
+
+fn main() {
+    let options = match parse_options() {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("mecab-rs-server: {}\n\n{}", e, USAGE);
+            process::exit(1);
+        }
+    };
+
+    let server = match Server::http(&options.listen) {
+        Ok(server) => Arc::new(server),
+        Err(e) => {
+            eprintln!("mecab-rs-server: {}: {}", options.listen, e);
+            process::exit(1);
+        }
+    };
+    println!("listening on http://{}", server.server_addr());
+
+    let state = Arc::new(State::new(options.max_body_size, options.timeout, options.max_nbest, options.max_readers));
+
+    // the model loads in the background so that /health answers right away
+    {
+        let state = state.clone();
+        let args = options.mecab_args.clone();
+        thread::spawn(move || {
+            let model = Model::with_args(&args);
+            let pool = if model.is_available() {
+                Ok(Arc::new(TaggerPool::new(model)))
+            } else {
+                Err(Tagger::with_args(&args).get_last_error())
+            };
+            if let Err(ref e) = pool {
+                eprintln!("mecab-rs-server: failed to load the model: {}", e);
+            }
+            state.set_pool(pool);
+        });
+    }
+
+    let workers: Vec<_> = (0..options.workers)
+        .map(|_| {
+            let server = server.clone();
+            let state = state.clone();
+            thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    api::handle(&state, request);
+                }
+            })
+        })
+        .collect();
+
+    for worker in workers {
+        let _ = worker.join();
+    }
+}
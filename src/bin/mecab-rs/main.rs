@@ -0,0 +1,151 @@
+extern crate mecab;
+extern crate serde;
+extern crate serde_json;
+
+mod options;
+mod output;
+mod worker;
+
+use std::env;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::iter;
+use std::process;
+
+use mecab::{Lattice, Model, Path, Tagger, MECAB_ALL_MORPH, MECAB_MARGINAL_PROB, MECAB_NBEST, MECAB_ONE_BEST,
+            MECAB_PARTIAL};
+
+use options::{Format, Options, USAGE};
+use output::Writer;
+
+fn main() {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let worker = args.first().is_some_and(|a| a == "worker");
+    if worker {
+        args.remove(0);
+    }
+
+    let options = match Options::parse(args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("mecab-rs: {}\n\n{}", e, USAGE);
+            process::exit(1);
+        }
+    };
+
+    if options.help {
+        print!("{}", USAGE);
+        return;
+    }
+    if options.version {
+        println!("mecab-rs {} (libmecab {})", env!("CARGO_PKG_VERSION"), mecab::version());
+        return;
+    }
+
+    let result = if worker { worker::run(&options) } else { run(&options) };
+    if let Err(e) = result {
+        eprintln!("mecab-rs: {}", e);
+        process::exit(1);
+    }
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    // lattices of the model carry the writer configured by -O and -F
+    let model = Model::with_args(&options.mecab_args);
+    if !model.is_available() {
+        return Err(Tagger::with_args(&options.mecab_args).get_last_error());
+    }
+    let tagger = model.create_tagger();
+    let mut lattice = model.create_lattice();
+
+    let out: Box<dyn Write> = match options.output {
+        Some(ref path) => Box::new(File::create(path).map_err(|e| format!("{}: {}", path, e))?),
+        None => Box::new(io::stdout()),
+    };
+    let mut out = BufWriter::new(out);
+    let mut writer = Writer::new(&mut out, options.format, options.nbest > 1);
+
+    let inputs = if options.inputs.is_empty() {
+        vec!["-".to_owned()]
+    } else {
+        options.inputs.clone()
+    };
+    for input in &inputs {
+        let reader: Box<dyn Read> = if input == "-" {
+            Box::new(io::stdin())
+        } else {
+            Box::new(File::open(input).map_err(|e| format!("{}: {}", input, e))?)
+        };
+
+        for sentence in sentences(BufReader::new(reader), options.partial) {
+            let sentence = sentence.map_err(|e| format!("{}: {}", input, e))?;
+            prepare(&tagger, &mut lattice, options, &sentence);
+
+            if options.format == Format::Mecab {
+                if !tagger.parse(&lattice) {
+                    return Err(lattice.what());
+                }
+                let text = if options.nbest > 1 {
+                    lattice.enum_nbest_as_string(options.nbest as i64)
+                } else {
+                    lattice.to_string()
+                };
+                writer.write_text(&text).map_err(|e| e.to_string())?;
+            } else {
+                let paths: Vec<Path> = tagger.nbest_lattice(&mut lattice, options.nbest).collect();
+                if paths.is_empty() {
+                    return Err(lattice.what());
+                }
+                writer.write_sentence(&sentence, &paths).map_err(|e| e.to_string())?;
+            }
+        }
+    }
+
+    writer.finish().map_err(|e| e.to_string())
+}
+
+fn prepare(tagger: &Tagger, lattice: &mut Lattice, options: &Options, sentence: &str) {
+    lattice.set_request_type(default_request_type(options));
+    lattice.set_theta(tagger.theta() as f64);
+    lattice.set_sentence(sentence);
+}
+
+fn default_request_type(options: &Options) -> i32 {
+    let mut request_type = if options.nbest > 1 { MECAB_NBEST } else { MECAB_ONE_BEST };
+    if options.partial {
+        request_type |= MECAB_PARTIAL;
+    }
+    if options.marginal {
+        request_type |= MECAB_MARGINAL_PROB;
+    }
+    if options.all_morphs {
+        request_type |= MECAB_ALL_MORPH;
+    }
+    request_type
+}
+
+// single lines, or lines up to EOS holding the constraints of a sentence in partial mode
+fn sentences<R: BufRead + 'static>(reader: R, partial: bool) -> Box<dyn Iterator<Item = io::Result<String>>> {
+    let mut lines = reader.lines();
+    if !partial {
+        return Box::new(lines);
+    }
+
+    Box::new(iter::from_fn(move || {
+        let mut block = String::new();
+        loop {
+            match lines.next() {
+                Some(Ok(line)) => {
+                    if line == "EOS" {
+                        return Some(Ok(block));
+                    }
+                    block.push_str(&line);
+                    block.push('\n');
+                }
+                Some(Err(e)) => return Some(Err(e)),
+                None if block.is_empty() => return None,
+                None => return Some(Ok(block)),
+            }
+        }
+    }))
+}
@@ -0,0 +1,191 @@
+use std::fmt;
+
+pub const USAGE: &str = "Usage: mecab-rs [options] [files]
+       mecab-rs worker [options]
+
+The worker reads one JSON request per line from stdin, such as
+{\"id\": 1, \"text\": \"...\", \"nbest\": 2, \"constraints\": [{\"kind\": \"token\", \"begin\": 0, \"end\": 2}]},
+and writes one JSON response per line with the same id.
+
+Options:
+  -d, --dicdir=DIR               set DIR as a system dicdir
+  -u, --userdic=FILE             use FILE as a user dictionary
+  -r, --rcfile=FILE              use FILE as a resource file
+  -O, --output-format-type=TYPE  set output format type (wakati, chasen, yomi, dump,
+                                 json, jsonl, tsv)
+  -N, --nbest=INT                output N best results
+  -p, --partial                  partial parsing mode
+  -m, --marginal                 output marginal probability
+  -a, --all-morphs               output all morphs
+  -l, --lattice-level=INT        lattice information level (deprecated)
+  -F, --node-format=STR          use STR as the user-defined node format
+  -U, --unk-format=STR           use STR as the user-defined unknown node format
+  -B, --bos-format=STR           use STR as the user-defined beginning-of-sentence format
+  -E, --eos-format=STR           use STR as the user-defined end-of-sentence format
+  -S, --eon-format=STR           use STR as the user-defined end-of-NBest format
+  -x, --unk-feature=STR          use STR as the feature for unknown word
+  -t, --theta=FLOAT              set temperature parameter theta
+  -o, --output=FILE              set the output file name
+  -h, --help                     show this help and exit
+  -v, --version                  show the version and exit
+";
+
+// short name, long name, takes a value
+const FLAGS: &[(char, &str, bool)] = &[('d', "dicdir", true),
+                                       ('u', "userdic", true),
+                                       ('r', "rcfile", true),
+                                       ('O', "output-format-type", true),
+                                       ('N', "nbest", true),
+                                       ('p', "partial", false),
+                                       ('m', "marginal", false),
+                                       ('a', "all-morphs", false),
+                                       ('l', "lattice-level", true),
+                                       ('F', "node-format", true),
+                                       ('U', "unk-format", true),
+                                       ('B', "bos-format", true),
+                                       ('E', "eos-format", true),
+                                       ('S', "eon-format", true),
+                                       ('x', "unk-feature", true),
+                                       ('t', "theta", true),
+                                       ('o', "output", true),
+                                       ('h', "help", false),
+                                       ('v', "version", false)];
+
+/// Output formats, `Mecab` leaves the formatting to libmecab.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Mecab,
+    Json,
+    JsonLines,
+    Tsv,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Options {
+    /// Arguments passed on to libmecab.
+    pub mecab_args: Vec<String>,
+    pub format: Format,
+    pub nbest: usize,
+    pub partial: bool,
+    pub marginal: bool,
+    pub all_morphs: bool,
+    pub output: Option<String>,
+    pub inputs: Vec<String>,
+    pub help: bool,
+    pub version: bool,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            mecab_args: Vec::new(),
+            format: Format::Mecab,
+            nbest: 1,
+            partial: false,
+            marginal: false,
+            all_morphs: false,
+            output: None,
+            inputs: Vec::new(),
+            help: false,
+            version: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OptionError(pub String);
+
+impl fmt::Display for OptionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Options {
+    /// Parses the arguments following the program name.
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Options, OptionError> {
+        let mut options = Options::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                options.inputs.extend(args.by_ref());
+                break;
+            }
+
+            let (flag, inline) = if let Some(long) = arg.strip_prefix("--") {
+                let (name, value) = match long.find('=') {
+                    Some(i) => (&long[..i], Some(long[i + 1..].to_owned())),
+                    None => (long, None),
+                };
+                let flag = FLAGS.iter()
+                    .find(|f| f.1 == name)
+                    .ok_or_else(|| OptionError(format!("unrecognized option `--{}`", name)))?;
+                (flag, value)
+            } else if arg.len() > 1 && arg.starts_with('-') {
+                let name = arg[1..].chars().next().unwrap();
+                let flag = FLAGS.iter()
+                    .find(|f| f.0 == name)
+                    .ok_or_else(|| OptionError(format!("unrecognized option `-{}`", name)))?;
+                let rest = &arg[1 + name.len_utf8()..];
+                (flag, if rest.is_empty() { None } else { Some(rest.to_owned()) })
+            } else {
+                options.inputs.push(arg);
+                continue;
+            };
+
+            let &(short, long, takes_value) = flag;
+            let value = if takes_value {
+                match inline {
+                    Some(value) => Some(value),
+                    None => {
+                        Some(args.next().ok_or_else(|| OptionError(format!("`--{}` requires an argument", long)))?)
+                    }
+                }
+            } else if inline.is_some() {
+                return Err(OptionError(format!("`--{}` does not take an argument", long)));
+            } else {
+                None
+            };
+
+            options.apply(short, long, value)?;
+        }
+
+        Ok(options)
+    }
+
+    fn apply(&mut self, short: char, long: &str, value: Option<String>) -> Result<(), OptionError> {
+        match short {
+            'O' => {
+                match value.as_deref() {
+                    Some("json") => self.format = Format::Json,
+                    Some("jsonl") => self.format = Format::JsonLines,
+                    Some("tsv") => self.format = Format::Tsv,
+                    _ => self.forward(long, value),
+                }
+            }
+            'N' => {
+                let value = value.unwrap();
+                self.nbest = match value.parse() {
+                    Ok(n) if (1..=512).contains(&n) => n,
+                    _ => return Err(OptionError(format!("invalid N value `{}`, N must be 1 <= N <= 512", value))),
+                };
+            }
+            'p' => self.partial = true,
+            'm' => self.marginal = true,
+            'a' => self.all_morphs = true,
+            'o' => self.output = value,
+            'h' => self.help = true,
+            'v' => self.version = true,
+            _ => self.forward(long, value),
+        }
+        Ok(())
+    }
+
+    fn forward(&mut self, long: &str, value: Option<String>) {
+        self.mecab_args.push(match value {
+            Some(value) => format!("--{}={}", long, value),
+            None => format!("--{}", long),
+        });
+    }
+}
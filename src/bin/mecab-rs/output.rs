@@ -0,0 +1,83 @@
+use std::io::{self, Write};
+
+use mecab::Path;
+use serde_json::{self, json, Value};
+
+use options::Format;
+
+/// Writes parsed sentences as JSON, JSON Lines or TSV, or passes on the text of libmecab.
+pub struct Writer<W: Write> {
+    out: W,
+    format: Format,
+    nbest: bool,
+    sentences: usize,
+}
+
+impl<W: Write> Writer<W> {
+    pub fn new(out: W, format: Format, nbest: bool) -> Writer<W> {
+        Writer {
+            out,
+            format,
+            nbest,
+            sentences: 0,
+        }
+    }
+
+    /// Writes output formatted by libmecab.
+    pub fn write_text(&mut self, text: &str) -> io::Result<()> {
+        self.sentences += 1;
+        self.out.write_all(text.as_bytes())
+    }
+
+    pub fn write_sentence(&mut self, sentence: &str, paths: &[Path]) -> io::Result<()> {
+        match self.format {
+            Format::Json | Format::JsonLines => {
+                let record = record(sentence, paths, self.nbest);
+                if self.format == Format::Json {
+                    write!(self.out, "{}\n  ", if self.sentences == 0 { "[" } else { "," })?;
+                }
+                serde_json::to_writer(&mut self.out, &record)?;
+                if self.format == Format::JsonLines {
+                    writeln!(self.out)?;
+                }
+            }
+            Format::Tsv => {
+                if self.sentences == 0 {
+                    writeln!(self.out, "sentence\tpath\tbegin\tend\tsurface\tfeature")?;
+                }
+                for (i, path) in paths.iter().enumerate() {
+                    for token in &path.tokens {
+                        writeln!(self.out,
+                                 "{}\t{}\t{}\t{}\t{}\t{}",
+                                 self.sentences,
+                                 i,
+                                 token.span.begin,
+                                 token.span.end,
+                                 token.surface,
+                                 token.feature)?;
+                    }
+                }
+            }
+            Format::Mecab => unreachable!("libmecab formats its own output"),
+        }
+        self.sentences += 1;
+        Ok(())
+    }
+
+    /// Closes the JSON array, other formats need no trailer.
+    pub fn finish(&mut self) -> io::Result<()> {
+        if self.format == Format::Json {
+            writeln!(self.out, "{}", if self.sentences == 0 { "[]" } else { "\n]" })?;
+        }
+        self.out.flush()
+    }
+}
+
+/// JSON object of a sentence, with all paths in n-best mode and the tokens of the best path otherwise.
+pub fn record(sentence: &str, paths: &[Path], nbest: bool) -> Value {
+    if nbest {
+        json!({ "sentence": sentence, "paths": paths })
+    } else {
+        json!({ "sentence": sentence, "tokens": paths.first().map(|p| &p.tokens[..]).unwrap_or(&[]) })
+    }
+}
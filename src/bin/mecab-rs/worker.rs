@@ -0,0 +1,143 @@
+use std::io::{self, BufRead, Write};
+
+use mecab::{Constraints, Lattice, Model, Path, Tagger};
+use serde::Deserialize;
+use serde_json::{self, json, Value};
+
+use options::Options;
+use output::record;
+use default_request_type;
+
+/// One line of worker input.
+///
+/// `request_type` is a bitmask of the `MECAB_*` request types and replaces
+/// the one given by the command-line flags.
+#[derive(Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: Value,
+    text: String,
+    #[serde(default)]
+    nbest: Option<usize>,
+    #[serde(default)]
+    request_type: Option<i32>,
+    #[serde(default)]
+    theta: Option<f64>,
+    #[serde(default)]
+    constraints: Vec<Constraint>,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Kind {
+    Token,
+    Inside,
+    Feature,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Unit {
+    Byte,
+    Char,
+}
+
+#[derive(Deserialize)]
+struct Constraint {
+    kind: Kind,
+    begin: usize,
+    end: usize,
+    #[serde(default = "default_unit")]
+    unit: Unit,
+    #[serde(default)]
+    feature: Option<String>,
+}
+
+fn default_unit() -> Unit {
+    Unit::Char
+}
+
+/// Answers newline-delimited JSON requests from stdin until it is closed.
+///
+/// Every response is a single line holding the `id` of its request, failed
+/// requests are answered with an `error` and do not stop the worker.
+pub fn run(options: &Options) -> Result<(), String> {
+    let model = Model::with_args(&options.mecab_args);
+    if !model.is_available() {
+        return Err(Tagger::with_args(&options.mecab_args).get_last_error());
+    }
+    let tagger = model.create_tagger();
+    let mut lattice = model.create_lattice();
+
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    for line in stdin.lock().lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => {
+                let id = request.id.clone();
+                match process(&tagger, &mut lattice, options, &request) {
+                    Ok(mut response) => {
+                        response["id"] = id;
+                        response
+                    }
+                    Err(e) => json!({ "id": id, "error": e }),
+                }
+            }
+            Err(e) => json!({ "id": Value::Null, "error": format!("invalid request: {}", e) }),
+        };
+
+        serde_json::to_writer(&mut out, &response).map_err(|e| e.to_string())?;
+        writeln!(out).and_then(|_| out.flush()).map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
+
+fn process(tagger: &Tagger, lattice: &mut Lattice, options: &Options, request: &Request) -> Result<Value, String> {
+    let nbest = request.nbest.unwrap_or(options.nbest);
+    if nbest == 0 || nbest > 512 {
+        return Err("nbest must be between 1 and 512".to_owned());
+    }
+
+    // `nbest_lattice` adds MECAB_NBEST, and applying constraints removes MECAB_PARTIAL
+    let request_type = request.request_type.unwrap_or_else(|| default_request_type(options));
+
+    if request.text.contains('\0') {
+        return Err("text must not contain NUL".to_owned());
+    }
+
+    lattice.clear();
+    lattice.set_request_type(request_type);
+    lattice.set_theta(request.theta.unwrap_or(tagger.theta() as f64));
+    lattice.set_sentence(request.text.as_str());
+
+    if !request.constraints.is_empty() {
+        let mut constraints = Constraints::new();
+        for c in &request.constraints {
+            let range = c.begin..c.end;
+            match (c.kind, c.unit, c.feature.as_deref()) {
+                (Kind::Token, Unit::Byte, _) => constraints.token_bytes(range),
+                (Kind::Token, Unit::Char, _) => constraints.token_chars(range),
+                (Kind::Inside, Unit::Byte, _) => constraints.inside_bytes(range),
+                (Kind::Inside, Unit::Char, _) => constraints.inside_chars(range),
+                (Kind::Feature, Unit::Byte, Some(f)) => constraints.feature_bytes(range, f),
+                (Kind::Feature, Unit::Char, Some(f)) => constraints.feature_chars(range, f),
+                (Kind::Feature, _, None) => return Err("feature constraints need a `feature`".to_owned()),
+            };
+        }
+        constraints.apply(lattice).map_err(|e| e.to_string())?;
+    }
+
+    let paths: Vec<Path> = tagger.nbest_lattice(lattice, nbest).collect();
+    if paths.is_empty() {
+        return Err(lattice.what());
+    }
+    Ok(record(&request.text, &paths, nbest > 1))
+}
@@ -0,0 +1,225 @@
+use token::{Span, Token};
+
+/// A content word with its prefixes and the function words that follow it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bunsetsu {
+    pub tokens: Vec<Token>,
+    /// Byte span within the sentence.
+    pub span: Span,
+    pub char_span: Span,
+    pub utf16_span: Span,
+    /// Index of the last content token.
+    pub head: usize,
+    /// Index of the first function token, `tokens.len()` if there is none.
+    pub tail: usize,
+}
+
+impl Bunsetsu {
+    fn new(token: Token) -> Bunsetsu {
+        Bunsetsu {
+            span: token.span,
+            char_span: token.char_span,
+            utf16_span: token.utf16_span,
+            tokens: vec![token],
+            head: 0,
+            tail: 1,
+        }
+    }
+
+    fn push(&mut self, token: Token, function: bool) {
+        self.span.end = token.span.end;
+        self.char_span.end = token.char_span.end;
+        self.utf16_span.end = token.utf16_span.end;
+        self.tokens.push(token);
+        if function {
+            self.tail = self.tail.min(self.tokens.len() - 1);
+        } else {
+            self.head = self.tokens.len() - 1;
+            self.tail = self.tokens.len();
+        }
+    }
+
+    pub fn head_token(&self) -> &Token {
+        &self.tokens[self.head]
+    }
+
+    /// Function words at the end of the chunk.
+    pub fn tail_tokens(&self) -> &[Token] {
+        &self.tokens[self.tail..]
+    }
+
+    pub fn surface(&self) -> String {
+        self.tokens.iter().map(|t| t.surface.as_str()).collect()
+    }
+}
+
+/// Feature prefix a token has to match, `*` matches any field.
+type PosPattern = Vec<String>;
+
+/// Groups tokens into bunsetsu following POS rules of a dictionary.
+#[derive(Clone, Debug, Default)]
+pub struct Chunker {
+    function: Vec<PosPattern>,
+    prefix: Vec<PosPattern>,
+    compound: Vec<PosPattern>,
+}
+
+impl Chunker {
+    /// A chunker without rules, every token starts a new bunsetsu.
+    pub fn new() -> Chunker {
+        Chunker::default()
+    }
+
+    pub fn ipadic() -> Chunker {
+        Chunker::new()
+            .function(&["助詞"])
+            .function(&["助動詞"])
+            .function(&["名詞", "接尾"])
+            .function(&["動詞", "非自立"])
+            .function(&["動詞", "接尾"])
+            .function(&["形容詞", "非自立"])
+            .function(&["形容詞", "接尾"])
+            .function(&["記号", "句点"])
+            .function(&["記号", "読点"])
+            .function(&["記号", "括弧閉"])
+            .prefix(&["接頭詞"])
+            .prefix(&["記号", "括弧開"])
+            .compound(&["名詞"])
+    }
+
+    pub fn unidic() -> Chunker {
+        Chunker::new()
+            .function(&["助詞"])
+            .function(&["助動詞"])
+            .function(&["接尾辞"])
+            .function(&["動詞", "非自立可能"])
+            .function(&["形容詞", "非自立可能"])
+            .function(&["補助記号", "句点"])
+            .function(&["補助記号", "読点"])
+            .function(&["補助記号", "括弧閉"])
+            .prefix(&["接頭辞"])
+            .prefix(&["補助記号", "括弧開"])
+            .compound(&["名詞"])
+    }
+
+    /// Tokens matching `pattern` attach to the preceding bunsetsu.
+    pub fn function(mut self, pattern: &[&str]) -> Chunker {
+        self.function.push(to_pattern(pattern));
+        self
+    }
+
+    /// Tokens matching `pattern` attach to the following content word.
+    pub fn prefix(mut self, pattern: &[&str]) -> Chunker {
+        self.prefix.push(to_pattern(pattern));
+        self
+    }
+
+    /// Adjacent content words that both match `pattern` form a single bunsetsu.
+    pub fn compound(mut self, pattern: &[&str]) -> Chunker {
+        self.compound.push(to_pattern(pattern));
+        self
+    }
+
+    pub fn chunk(&self, tokens: &[Token]) -> Vec<Bunsetsu> {
+        let mut chunks: Vec<Bunsetsu> = Vec::new();
+        let mut after_prefix = false;
+
+        for token in tokens {
+            let token = token.clone();
+            let current = chunks.last_mut();
+
+            if after_prefix {
+                let prefix = matches(&self.prefix, &token);
+                current.unwrap().push(token, false);
+                after_prefix = prefix;
+            } else if matches(&self.prefix, &token) {
+                chunks.push(Bunsetsu::new(token));
+                after_prefix = true;
+            } else if let Some(current) = current {
+                if matches(&self.function, &token) {
+                    current.push(token, true);
+                } else if current.tail == current.tokens.len() &&
+                          matches(&self.compound, current.head_token()) &&
+                          matches(&self.compound, &token) {
+                    current.push(token, false);
+                } else {
+                    chunks.push(Bunsetsu::new(token));
+                }
+            } else {
+                chunks.push(Bunsetsu::new(token));
+            }
+        }
+
+        chunks
+    }
+}
+
+fn to_pattern(pattern: &[&str]) -> PosPattern {
+    pattern.iter().map(|&field| field.to_owned()).collect()
+}
+
+fn matches(patterns: &[PosPattern], token: &Token) -> bool {
+    patterns.iter().any(|pattern| token.matches_pos(pattern))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use format::{read_sentences, OutputFormat};
+
+    fn tokens(lines: &[(&str, &str)]) -> Vec<Token> {
+        let text: String = lines.iter().map(|&(surface, feature)| format!("{}\t{}\n", surface, feature)).collect();
+        read_sentences(&(text + "EOS\n"), OutputFormat::Default).unwrap().remove(0)
+    }
+
+    fn surfaces(chunks: &[Bunsetsu]) -> Vec<String> {
+        chunks.iter().map(Bunsetsu::surface).collect()
+    }
+
+    #[test]
+    fn prefixes_and_function_words() {
+        let tokens = tokens(&[("お", "接頭詞,名詞接続,*,*,*,*,お,オ,オ"),
+                              ("茶", "名詞,一般,*,*,*,*,茶,チャ,チャ"),
+                              ("を", "助詞,格助詞,一般,*,*,*,を,ヲ,ヲ"),
+                              ("飲ん", "動詞,自立,*,*,五段・マ行,連用タ接続,飲む,ノン,ノン"),
+                              ("だ", "助動詞,*,*,*,特殊・タ,基本形,だ,ダ,ダ"),
+                              ("。", "記号,句点,*,*,*,*,。,。,。")]);
+        let chunks = Chunker::ipadic().chunk(&tokens);
+        assert_eq!(surfaces(&chunks), ["お茶を", "飲んだ。"]);
+
+        assert_eq!(chunks[0].head_token().surface, "茶");
+        assert_eq!(chunks[0].tail_tokens().len(), 1);
+        assert_eq!(chunks[1].head_token().surface, "飲ん");
+        assert_eq!(chunks[1].tail_tokens().len(), 2);
+        assert_eq!(chunks[1].span, Span::new(9, 21));
+        assert_eq!(chunks[1].char_span, Span::new(3, 7));
+    }
+
+    #[test]
+    fn compounds() {
+        let tokens = tokens(&[("東京", "名詞,固有名詞,地域,一般,*,*,東京,トウキョウ,トーキョー"),
+                              ("大学", "名詞,一般,*,*,*,*,大学,ダイガク,ダイガク"),
+                              ("に", "助詞,格助詞,一般,*,*,*,に,ニ,ニ"),
+                              ("大学", "名詞,一般,*,*,*,*,大学,ダイガク,ダイガク")]);
+        // a noun after a function word starts a new bunsetsu
+        assert_eq!(surfaces(&Chunker::ipadic().chunk(&tokens)), ["東京大学に", "大学"]);
+        assert_eq!(Chunker::ipadic().chunk(&tokens)[0].head, 1);
+    }
+
+    #[test]
+    fn without_rules() {
+        let tokens = tokens(&[("すもも", "名詞,一般,*,*,*,*,すもも,スモモ,スモモ"),
+                              ("も", "助詞,係助詞,*,*,*,*,も,モ,モ")]);
+        assert_eq!(surfaces(&Chunker::new().chunk(&tokens)), ["すもも", "も"]);
+        assert!(Chunker::ipadic().chunk(&[]).is_empty());
+    }
+
+    #[test]
+    fn leading_function_word() {
+        let tokens = tokens(&[("も", "助詞,係助詞,*,*,*,*,も,モ,モ"),
+                              ("もも", "名詞,一般,*,*,*,*,もも,モモ,モモ")]);
+        let chunks = Chunker::ipadic().chunk(&tokens);
+        assert_eq!(surfaces(&chunks), ["も", "もも"]);
+        assert_eq!(chunks[0].head, 0);
+    }
+}
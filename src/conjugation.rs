@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+
+use format::FormatError;
+use token::{split_features, Token};
+
+const IPADIC_TABLE: &str = include_str!("ipadic_conjugation.csv");
+const UNIDIC_TABLE: &str = include_str!("unidic_conjugation.csv");
+
+/// Fields of an IPADIC feature, UniDic features have more.
+const IPADIC_FIELDS: usize = 9;
+/// Field of a UniDic feature holding the base form in the written form of the token (書字形基本形).
+const UNIDIC_BASE_FORM: usize = 10;
+
+/// A conjugated form of a lemma.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Inflection {
+    /// Conjugation form name such as `未然形`.
+    pub form: String,
+    pub surface: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Rule {
+    form: String,
+    ending: String,
+    replacement: String,
+}
+
+/// Conjugation rules keyed by conjugation type.
+///
+/// Every rule replaces an ending of the base form, so `書く` with the
+/// `五段・カ行イ音便` rule `連用タ接続,く,い` becomes `書い`.
+#[derive(Clone, Debug, Default)]
+pub struct ConjugationTable {
+    types: HashMap<String, Vec<Rule>>,
+}
+
+impl ConjugationTable {
+    pub fn new() -> ConjugationTable {
+        ConjugationTable::default()
+    }
+
+    /// Verb and adjective conjugations with the type and form names of IPADIC.
+    pub fn ipadic() -> ConjugationTable {
+        ConjugationTable::parse(IPADIC_TABLE).unwrap()
+    }
+
+    /// Verb and adjective conjugations of contemporary UniDic, such as `五段-カ行` and `連用形-イ音便`.
+    pub fn unidic() -> ConjugationTable {
+        ConjugationTable::parse(UNIDIC_TABLE).unwrap()
+    }
+
+    /// Reads rules from CSV lines of `type,form,ending,replacement`.
+    ///
+    /// Empty lines and lines starting with `#` are skipped.
+    pub fn parse(text: &str) -> Result<ConjugationTable, FormatError> {
+        let mut table = ConjugationTable::new();
+        for (i, line) in text.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match split_features(line)[..] {
+                [ctype, form, ending, replacement] => {
+                    table.add_rule(ctype, form, ending, replacement);
+                }
+                ref fields => {
+                    return Err(FormatError::new(i + 1, format!("expected 4 fields, found {}", fields.len())));
+                }
+            }
+        }
+        Ok(table)
+    }
+
+    /// Adds a rule, a form may have several rules for alternative surfaces.
+    pub fn add_rule(&mut self, ctype: &str, form: &str, ending: &str, replacement: &str) -> &mut ConjugationTable {
+        self.types.entry(ctype.to_owned()).or_default().push(Rule {
+            form: form.to_owned(),
+            ending: ending.to_owned(),
+            replacement: replacement.to_owned(),
+        });
+        self
+    }
+
+    pub fn conjugation_types(&self) -> Vec<&str> {
+        let mut types: Vec<&str> = self.types.keys().map(String::as_str).collect();
+        types.sort();
+        types
+    }
+
+    /// Every form of `lemma`, `None` if the type is unknown or the lemma does not have its ending.
+    pub fn conjugate(&self, lemma: &str, ctype: &str) -> Option<Vec<Inflection>> {
+        let inflections: Vec<Inflection> = self.types
+            .get(ctype)?
+            .iter()
+            .filter(|rule| lemma.ends_with(&rule.ending[..]))
+            .map(|rule| {
+                let stem = &lemma[..lemma.len() - rule.ending.len()];
+                Inflection {
+                    form: rule.form.clone(),
+                    surface: format!("{}{}", stem, rule.replacement),
+                }
+            })
+            .collect();
+
+        if inflections.is_empty() {
+            None
+        } else {
+            Some(inflections)
+        }
+    }
+
+    /// Surface of `lemma` in a single conjugation form.
+    pub fn inflect(&self, lemma: &str, ctype: &str, form: &str) -> Option<String> {
+        self.conjugate(lemma, ctype)?
+            .into_iter()
+            .find(|inflection| inflection.form == form)
+            .map(|inflection| inflection.surface)
+    }
+
+    /// Every form of the token's lemma, `None` for words that do not conjugate.
+    ///
+    /// Features with more fields than IPADIC are read as UniDic features, whose
+    /// lemma is the written base form. The table has to be the one of the
+    /// token's dictionary, `ipadic` or `unidic`.
+    pub fn token_conjugations(&self, token: &Token) -> Option<Vec<Inflection>> {
+        let features = token.features();
+        let lemma = if features.len() > IPADIC_FIELDS {
+            features.get(UNIDIC_BASE_FORM).cloned().filter(|base| *base != "*")?
+        } else {
+            token.lemma()
+        };
+        self.conjugate(lemma, token.conjugation_type()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use format::{read_sentences, OutputFormat};
+
+    #[test]
+    fn ipadic_verbs_and_adjectives() {
+        let table = ConjugationTable::ipadic();
+        assert_eq!(table.inflect("書く", "五段・カ行イ音便", "連用タ接続").as_deref(), Some("書い"));
+        assert_eq!(table.inflect("食べる", "一段", "未然形").as_deref(), Some("食べ"));
+        assert_eq!(table.inflect("勉強する", "サ変・スル", "仮定形").as_deref(), Some("勉強すれ"));
+        assert_eq!(table.inflect("高い", "形容詞・アウオ段", "連用タ接続").as_deref(), Some("高かっ"));
+        assert!(table.conjugation_types().contains(&"一段"));
+    }
+
+    #[test]
+    fn unidic_verbs_and_adjectives() {
+        let table = ConjugationTable::unidic();
+        assert_eq!(table.inflect("書く", "五段-カ行", "連用形-イ音便").as_deref(), Some("書い"));
+        assert_eq!(table.inflect("読む", "五段-マ行", "連用形-撥音便").as_deref(), Some("読ん"));
+        assert_eq!(table.inflect("買う", "五段-ワア行", "未然形-一般").as_deref(), Some("買わ"));
+        assert_eq!(table.inflect("食べる", "下一段-バ行", "未然形-一般").as_deref(), Some("食べ"));
+        assert_eq!(table.inflect("起きる", "上一段-カ行", "仮定形-一般").as_deref(), Some("起きれ"));
+        assert_eq!(table.inflect("来る", "カ行変格", "命令形").as_deref(), Some("来い"));
+        assert_eq!(table.inflect("くる", "カ行変格", "未然形-一般").as_deref(), Some("こ"));
+        assert_eq!(table.inflect("する", "サ行変格", "未然形-サ").as_deref(), Some("さ"));
+        assert_eq!(table.inflect("高い", "形容詞", "連用形-促音便").as_deref(), Some("高かっ"));
+        // the tables do not share type names
+        assert_eq!(table.conjugate("書く", "五段・カ行イ音便"), None);
+    }
+
+    #[test]
+    fn forms_with_several_surfaces() {
+        let table = ConjugationTable::ipadic();
+        let forms: Vec<String> = table.conjugate("食べる", "一段")
+            .unwrap()
+            .into_iter()
+            .filter(|i| i.form.starts_with("命令"))
+            .map(|i| i.surface)
+            .collect();
+        assert_eq!(forms, ["食べろ", "食べよ"]);
+    }
+
+    #[test]
+    fn unknown_type_or_ending() {
+        let table = ConjugationTable::ipadic();
+        assert_eq!(table.conjugate("書く", "五段・ナ行"), None);
+        assert_eq!(table.conjugate("食べた", "一段"), None);
+        assert_eq!(table.inflect("書く", "五段・カ行イ音便", "存在しない形"), None);
+    }
+
+    #[test]
+    fn parse_custom_table() {
+        let table = ConjugationTable::parse("# comment\n\n動詞,終止形,う,う\n動詞,連用形,う,い\n").unwrap();
+        assert_eq!(table.inflect("買う", "動詞", "連用形").as_deref(), Some("買い"));
+
+        let e = ConjugationTable::parse("動詞,終止形,う\n").unwrap_err();
+        assert_eq!(e.line, 1);
+    }
+
+    #[test]
+    fn token_conjugations() {
+        let table = ConjugationTable::ipadic();
+        let tokens = read_sentences("書い\t動詞,自立,*,*,五段・カ行イ音便,連用タ接続,書く,カイ,カイ\n\
+                                     た\t助動詞,*,*,*,特殊・タ,基本形,た,タ,タ\n\
+                                     本\t名詞,一般,*,*,*,*,本,ホン,ホン\n\
+                                     EOS\n",
+                                    OutputFormat::Default)
+            .unwrap()
+            .remove(0);
+
+        let forms = table.token_conjugations(&tokens[0]).unwrap();
+        assert!(forms.contains(&Inflection {
+            form: "基本形".to_owned(),
+            surface: "書く".to_owned(),
+        }));
+        assert_eq!(table.token_conjugations(&tokens[2]), None);
+    }
+
+    #[test]
+    fn unidic_token_conjugations() {
+        let table = ConjugationTable::unidic();
+        let tokens = read_sentences("書い\t動詞,一般,*,*,五段-カ行,連用形-イ音便,カク,書く,書い,カイ,書く,カク,和,*,*,*,*\n\
+                                     し\t動詞,非自立可能,*,*,サ行変格,連用形-一般,スル,為る,し,シ,する,スル,和,*,*,*,*\n\
+                                     本\t名詞,普通名詞,一般,*,*,*,ホン,本,本,ホン,本,ホン,漢,*,*,*,*\n\
+                                     EOS\n",
+                                    OutputFormat::Default)
+            .unwrap()
+            .remove(0);
+
+        // the lemma field holds the reading カク and the lexeme 為る, the base form is used instead
+        let forms = table.token_conjugations(&tokens[0]).unwrap();
+        assert!(forms.contains(&Inflection {
+            form: "仮定形-一般".to_owned(),
+            surface: "書け".to_owned(),
+        }));
+        assert!(table.token_conjugations(&tokens[1]).unwrap().iter().any(|i| i.surface == "すれ"));
+        assert_eq!(table.token_conjugations(&tokens[2]), None);
+        assert_eq!(ConjugationTable::ipadic().token_conjugations(&tokens[0]), None);
+    }
+}
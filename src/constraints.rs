@@ -0,0 +1,380 @@
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
+
+use mecab::{Lattice, MECAB_ANY_BOUNDARY, MECAB_INSIDE_TOKEN, MECAB_PARTIAL, MECAB_TOKEN_BOUNDARY};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Unit {
+    Byte,
+    Char,
+}
+
+#[derive(Clone, Debug)]
+enum Kind {
+    Token,
+    Inside,
+    Feature(String),
+}
+
+#[derive(Clone, Debug)]
+struct Constraint {
+    unit: Unit,
+    range: Range<usize>,
+    kind: Kind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConstraintError {
+    /// The range is empty or reversed.
+    EmptyRange(Range<usize>),
+    /// The range reaches past the end of the sentence.
+    OutOfBounds(Range<usize>),
+    /// A byte offset does not fall on a character boundary.
+    NotCharBoundary(usize),
+    /// A byte position is required to be both a token boundary and inside a token.
+    Conflict(usize),
+    /// Two feature constraints overlap.
+    Overlap(Range<usize>, Range<usize>),
+    /// The feature pattern is empty or contains a NUL byte.
+    InvalidFeature(String),
+}
+
+impl fmt::Display for ConstraintError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConstraintError::EmptyRange(r) => write!(f, "empty range {}..{}", r.start, r.end),
+            ConstraintError::OutOfBounds(r) => {
+                write!(f, "range {}..{} is out of the sentence bounds", r.start, r.end)
+            }
+            ConstraintError::NotCharBoundary(pos) => {
+                write!(f, "byte offset {} is not on a character boundary", pos)
+            }
+            ConstraintError::Conflict(pos) => {
+                write!(f, "byte offset {} is both a token boundary and inside a token", pos)
+            }
+            ConstraintError::Overlap(a, b) => {
+                write!(f,
+                       "feature constraints {}..{} and {}..{} overlap",
+                       a.start,
+                       a.end,
+                       b.start,
+                       b.end)
+            }
+            ConstraintError::InvalidFeature(s) => write!(f, "invalid feature pattern {:?}", s),
+        }
+    }
+}
+
+impl Error for ConstraintError {}
+
+/// Per-byte boundary types and feature spans of a validated constraint set.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResolvedConstraints {
+    pub boundaries: Vec<i32>,
+    pub features: Vec<(Range<usize>, String)>,
+}
+
+/// Builder for token boundary and feature constraints.
+///
+/// Ranges are given either in bytes or in chars of the sentence and are only
+/// checked once the sentence is known, i.e. in `resolve` or `apply`.
+#[derive(Clone, Debug, Default)]
+pub struct Constraints {
+    constraints: Vec<Constraint>,
+}
+
+impl Constraints {
+    pub fn new() -> Constraints {
+        Constraints { constraints: Vec::new() }
+    }
+
+    /// Requires a token to start at `range.start` and a token to end at `range.end`.
+    pub fn token_bytes(&mut self, range: Range<usize>) -> &mut Constraints {
+        self.push(Unit::Byte, range, Kind::Token)
+    }
+
+    pub fn token_chars(&mut self, range: Range<usize>) -> &mut Constraints {
+        self.push(Unit::Char, range, Kind::Token)
+    }
+
+    /// Forbids any token boundary strictly inside `range`.
+    pub fn inside_bytes(&mut self, range: Range<usize>) -> &mut Constraints {
+        self.push(Unit::Byte, range, Kind::Inside)
+    }
+
+    pub fn inside_chars(&mut self, range: Range<usize>) -> &mut Constraints {
+        self.push(Unit::Char, range, Kind::Inside)
+    }
+
+    /// Forces `range` into a single token whose feature matches `pattern`,
+    /// e.g. `名詞,固有名詞,*`.
+    pub fn feature_bytes<T: Into<String>>(&mut self,
+                                          range: Range<usize>,
+                                          pattern: T)
+                                          -> &mut Constraints {
+        self.push(Unit::Byte, range, Kind::Feature(pattern.into()))
+    }
+
+    pub fn feature_chars<T: Into<String>>(&mut self,
+                                          range: Range<usize>,
+                                          pattern: T)
+                                          -> &mut Constraints {
+        self.push(Unit::Char, range, Kind::Feature(pattern.into()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.constraints.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.constraints.clear();
+    }
+
+    fn push(&mut self, unit: Unit, range: Range<usize>, kind: Kind) -> &mut Constraints {
+        self.constraints.push(Constraint {
+            unit,
+            range,
+            kind,
+        });
+        self
+    }
+
+    /// Validates the constraints against `sentence` and converts them to byte positions.
+    pub fn resolve(&self, sentence: &str) -> Result<ResolvedConstraints, ConstraintError> {
+        let mut boundaries = vec![MECAB_ANY_BOUNDARY; sentence.len() + 1];
+        let mut features: Vec<(Range<usize>, String)> = Vec::new();
+
+        for c in &self.constraints {
+            let range = to_bytes(sentence, c.unit, &c.range)?;
+
+            mark(&mut boundaries, range.start, &c.kind)?;
+            mark(&mut boundaries, range.end, &c.kind)?;
+
+            let inner = match c.kind {
+                Kind::Token => Vec::new(),
+                _ => {
+                    sentence[range.clone()]
+                        .char_indices()
+                        .skip(1)
+                        .map(|(i, _)| range.start + i)
+                        .collect()
+                }
+            };
+            for pos in inner {
+                if boundaries[pos] == MECAB_TOKEN_BOUNDARY {
+                    return Err(ConstraintError::Conflict(pos));
+                }
+                boundaries[pos] = MECAB_INSIDE_TOKEN;
+            }
+
+            if let Kind::Feature(ref pattern) = c.kind {
+                if pattern.is_empty() || pattern.contains('\0') {
+                    return Err(ConstraintError::InvalidFeature(pattern.clone()));
+                }
+                if let Some((other, _)) = features.iter()
+                    .find(|(r, _)| r.start < range.end && range.start < r.end) {
+                    return Err(ConstraintError::Overlap(other.clone(), range));
+                }
+                features.push((range, pattern.clone()));
+            }
+        }
+
+        Ok(ResolvedConstraints {
+            boundaries,
+            features,
+        })
+    }
+
+    /// Validates the constraints against the lattice sentence and sets them.
+    /// Must be called after `Lattice::set_sentence`.
+    ///
+    /// Removes `MECAB_PARTIAL` from the request type, since a partial parse
+    /// re-reads the sentence and drops the constraints; other request types
+    /// are kept. On error the lattice is left unchanged.
+    pub fn apply(&self, lattice: &mut Lattice) -> Result<(), ConstraintError> {
+        let resolved = self.resolve(&lattice.sentence())?;
+
+        for (pos, &boundary) in resolved.boundaries.iter().enumerate() {
+            if boundary != MECAB_ANY_BOUNDARY {
+                lattice.set_boundary_constraint(pos as u64, boundary);
+            }
+        }
+        for (range, pattern) in resolved.features {
+            lattice.set_feature_constraint(range.start as u64, range.end as u64, pattern);
+        }
+        // with MECAB_PARTIAL libmecab re-reads the sentence as partial input,
+        // which resets the constraints set above
+        lattice.remove_request_type(MECAB_PARTIAL);
+
+        Ok(())
+    }
+}
+
+fn to_bytes(sentence: &str, unit: Unit, range: &Range<usize>) -> Result<Range<usize>, ConstraintError> {
+    if range.start >= range.end {
+        return Err(ConstraintError::EmptyRange(range.clone()));
+    }
+
+    match unit {
+        Unit::Byte => {
+            if range.end > sentence.len() {
+                return Err(ConstraintError::OutOfBounds(range.clone()));
+            }
+            for &pos in &[range.start, range.end] {
+                if !sentence.is_char_boundary(pos) {
+                    return Err(ConstraintError::NotCharBoundary(pos));
+                }
+            }
+            Ok(range.clone())
+        }
+        Unit::Char => {
+            let mut offsets = sentence.char_indices().map(|(i, _)| i).chain(Some(sentence.len()));
+            let start = offsets.nth(range.start);
+            let end = offsets.nth(range.end - range.start - 1);
+            match (start, end) {
+                (Some(start), Some(end)) => Ok(start..end),
+                _ => Err(ConstraintError::OutOfBounds(range.clone())),
+            }
+        }
+    }
+}
+
+fn mark(boundaries: &mut [i32], pos: usize, kind: &Kind) -> Result<(), ConstraintError> {
+    if let Kind::Inside = *kind {
+        // an inside region leaves its edges unconstrained
+        return Ok(());
+    }
+    if boundaries[pos] == MECAB_INSIDE_TOKEN {
+        return Err(ConstraintError::Conflict(pos));
+    }
+    boundaries[pos] = MECAB_TOKEN_BOUNDARY;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mecab::{MECAB_MARGINAL_PROB, MECAB_NBEST, MECAB_ONE_BEST};
+
+    #[test]
+    fn resolve_chars_to_bytes() {
+        let mut constraints = Constraints::new();
+        constraints.token_chars(0..2).feature_chars(2..5, "名詞");
+        let resolved = constraints.resolve("東京タワーへ").unwrap();
+
+        assert_eq!(resolved.boundaries[0], MECAB_TOKEN_BOUNDARY);
+        assert_eq!(resolved.boundaries[6], MECAB_TOKEN_BOUNDARY);
+        assert_eq!(resolved.boundaries[9], MECAB_INSIDE_TOKEN);
+        assert_eq!(resolved.boundaries[12], MECAB_INSIDE_TOKEN);
+        assert_eq!(resolved.boundaries[15], MECAB_TOKEN_BOUNDARY);
+        assert_eq!(resolved.boundaries[18], MECAB_ANY_BOUNDARY);
+        assert_eq!(resolved.features, vec![(6..15, "名詞".to_owned())]);
+    }
+
+    #[test]
+    fn resolve_inside_leaves_edges_free() {
+        let mut constraints = Constraints::new();
+        constraints.inside_bytes(3..9);
+        let resolved = constraints.resolve("東京タワー").unwrap();
+
+        assert_eq!(resolved.boundaries[3], MECAB_ANY_BOUNDARY);
+        assert_eq!(resolved.boundaries[6], MECAB_INSIDE_TOKEN);
+        assert_eq!(resolved.boundaries[9], MECAB_ANY_BOUNDARY);
+    }
+
+    #[test]
+    fn resolve_out_of_bounds() {
+        let mut constraints = Constraints::new();
+        constraints.token_chars(3..6);
+        assert_eq!(constraints.resolve("東京タワー").unwrap_err(),
+                   ConstraintError::OutOfBounds(3..6));
+
+        constraints.clear();
+        constraints.token_bytes(0..16);
+        assert_eq!(constraints.resolve("東京タワー").unwrap_err(),
+                   ConstraintError::OutOfBounds(0..16));
+    }
+
+    #[test]
+    fn resolve_empty_range() {
+        let mut constraints = Constraints::new();
+        constraints.token_chars(2..2);
+        assert_eq!(constraints.resolve("東京").unwrap_err(), ConstraintError::EmptyRange(2..2));
+    }
+
+    #[test]
+    fn resolve_not_char_boundary() {
+        let mut constraints = Constraints::new();
+        constraints.token_bytes(0..4);
+        assert_eq!(constraints.resolve("東京").unwrap_err(), ConstraintError::NotCharBoundary(4));
+    }
+
+    #[test]
+    fn resolve_token_inside_conflict() {
+        let mut constraints = Constraints::new();
+        constraints.inside_chars(0..3).token_chars(1..2);
+        assert_eq!(constraints.resolve("東京都庁").unwrap_err(), ConstraintError::Conflict(3));
+
+        constraints.clear();
+        constraints.token_chars(0..2).inside_chars(1..3);
+        assert_eq!(constraints.resolve("東京都庁").unwrap_err(), ConstraintError::Conflict(6));
+    }
+
+    #[test]
+    fn resolve_overlapping_features() {
+        // partly overlapping features put an edge inside the other token
+        let mut constraints = Constraints::new();
+        constraints.feature_chars(0..2, "名詞").feature_chars(1..3, "名詞");
+        assert_eq!(constraints.resolve("東京都").unwrap_err(), ConstraintError::Conflict(3));
+
+        constraints.clear();
+        constraints.feature_chars(0..2, "名詞").feature_chars(0..2, "名詞,固有名詞");
+        assert_eq!(constraints.resolve("東京都").unwrap_err(),
+                   ConstraintError::Overlap(0..6, 0..6));
+    }
+
+    #[test]
+    fn resolve_invalid_feature() {
+        let mut constraints = Constraints::new();
+        constraints.feature_chars(0..2, "");
+        assert_eq!(constraints.resolve("東京").unwrap_err(),
+                   ConstraintError::InvalidFeature(String::new()));
+    }
+
+    #[test]
+    fn apply_sets_constraints_without_partial() {
+        let mut lattice = Lattice::new();
+        lattice.set_sentence("東京タワー");
+        lattice.set_request_type(MECAB_ONE_BEST | MECAB_PARTIAL);
+
+        let mut constraints = Constraints::new();
+        constraints.feature_chars(0..2, "名詞,固有名詞").token_chars(2..5);
+        constraints.apply(&mut lattice).unwrap();
+
+        assert!(!lattice.has_request_type(MECAB_PARTIAL));
+        assert!(lattice.has_constraint());
+        assert_eq!(lattice.boundary_constraint(0), MECAB_TOKEN_BOUNDARY);
+        assert_eq!(lattice.boundary_constraint(3), MECAB_INSIDE_TOKEN);
+        assert_eq!(lattice.boundary_constraint(6), MECAB_TOKEN_BOUNDARY);
+        assert_eq!(lattice.feature_constraint(0), "名詞,固有名詞");
+    }
+
+    #[test]
+    fn apply_keeps_other_request_types() {
+        let mut lattice = Lattice::new();
+        lattice.set_sentence("東京タワー");
+        lattice.set_request_type(MECAB_NBEST | MECAB_MARGINAL_PROB | MECAB_PARTIAL);
+
+        let mut constraints = Constraints::new();
+        constraints.token_chars(0..9);
+        assert_eq!(constraints.apply(&mut lattice).unwrap_err(), ConstraintError::OutOfBounds(0..9));
+        assert_eq!(lattice.request_type(), MECAB_NBEST | MECAB_MARGINAL_PROB | MECAB_PARTIAL);
+        assert!(!lattice.has_constraint());
+
+        constraints.clear();
+        constraints.token_chars(0..2);
+        constraints.apply(&mut lattice).unwrap();
+        assert_eq!(lattice.request_type(), MECAB_NBEST | MECAB_MARGINAL_PROB);
+    }
+}
@@ -0,0 +1,173 @@
+use mecab::{Lattice, Model};
+use kana::{is_kanji, is_katakana};
+use offset::OffsetMap;
+use token::{is_boundary_node, Token};
+
+/// A token of search-mode output with its position in the token stream.
+///
+/// A decompounded noun is emitted first with a `position_length` covering
+/// its sub-words, which follow with one position each.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchToken {
+    pub token: Token,
+    pub position: usize,
+    pub position_length: usize,
+}
+
+/// Splits long kanji and katakana nouns of the best path into sub-words.
+///
+/// Sub-words are the cheapest path through the lattice nodes inside the
+/// compound once every long node is penalized by its length.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SearchMode {
+    kanji_len: usize,
+    kanji_penalty: i64,
+    katakana_len: usize,
+    katakana_penalty: i64,
+}
+
+impl Default for SearchMode {
+    fn default() -> SearchMode {
+        SearchMode {
+            kanji_len: 2,
+            kanji_penalty: 3000,
+            katakana_len: 7,
+            katakana_penalty: 1700,
+        }
+    }
+}
+
+impl SearchMode {
+    pub fn new() -> SearchMode {
+        SearchMode::default()
+    }
+
+    /// Adds `penalty` for every kanji beyond `len` chars.
+    pub fn kanji_penalty(mut self, len: usize, penalty: i64) -> SearchMode {
+        self.kanji_len = len;
+        self.kanji_penalty = penalty;
+        self
+    }
+
+    /// Adds `penalty` for every katakana beyond `len` chars.
+    pub fn katakana_penalty(mut self, len: usize, penalty: i64) -> SearchMode {
+        self.katakana_len = len;
+        self.katakana_penalty = penalty;
+        self
+    }
+
+    /// Tokens of the best path of a parsed lattice with long nouns followed by their sub-words.
+    pub fn tokens(&self, lattice: &Lattice, model: &Model) -> Vec<SearchToken> {
+        let best = lattice.tokens();
+        let offsets = OffsetMap::new(&lattice.sentence());
+        let mut tokens = Vec::with_capacity(best.len());
+        let mut position = 0;
+
+        for (i, token) in best.iter().enumerate() {
+            let left = if i > 0 { best[i - 1].rcattr } else { 0 };
+            let right = best.get(i + 1).map_or(0, |t| t.lcattr);
+
+            match self.decompound(lattice, model, &offsets, token, left, right) {
+                Some(parts) => {
+                    tokens.push(SearchToken {
+                        token: token.clone(),
+                        position,
+                        position_length: parts.len(),
+                    });
+                    for part in parts {
+                        tokens.push(SearchToken {
+                            token: part,
+                            position,
+                            position_length: 1,
+                        });
+                        position += 1;
+                    }
+                }
+                None => {
+                    tokens.push(SearchToken {
+                        token: token.clone(),
+                        position,
+                        position_length: 1,
+                    });
+                    position += 1;
+                }
+            }
+        }
+
+        tokens
+    }
+
+    fn penalty(&self, surface: &str) -> i64 {
+        let len = surface.chars().count();
+        if len > self.kanji_len && surface.chars().all(is_kanji) {
+            (len - self.kanji_len) as i64 * self.kanji_penalty
+        } else if len > self.katakana_len && surface.chars().all(is_katakana) {
+            (len - self.katakana_len) as i64 * self.katakana_penalty
+        } else {
+            0
+        }
+    }
+
+    // cheapest path of at least two nodes inside the span of `token`
+    fn decompound(&self,
+                  lattice: &Lattice,
+                  model: &Model,
+                  offsets: &OffsetMap,
+                  token: &Token,
+                  left: u16,
+                  right: u16)
+                  -> Option<Vec<Token>> {
+        if token.pos() != "名詞" || self.penalty(&token.surface) == 0 {
+            return None;
+        }
+
+        let mut candidates = Vec::new();
+        for pos in token.span.begin..token.span.end {
+            if let Some(nodes) = lattice.begin_nodes(pos) {
+                candidates.extend(nodes.iter_bnext()
+                    .filter(|node| !is_boundary_node(node))
+                    .map(|node| Token::from_node(&node, offsets))
+                    .filter(|t| !t.span.is_empty() && t.span.begin >= token.span.begin && t.span.end <= token.span.end)
+                    // the whole token would win whenever it is cheapest and leave nothing to split
+                    .filter(|t| t.span != token.span));
+            }
+        }
+        candidates.sort_by_key(|t| t.span.begin);
+
+        // best cost of a path ending with each candidate and its predecessor
+        let mut best: Vec<Option<(i64, Option<usize>)>> = vec![None; candidates.len()];
+        for i in 0..candidates.len() {
+            let node = &candidates[i];
+            let cost = node.wcost as i64 + self.penalty(&node.surface);
+
+            best[i] = if node.span.begin == token.span.begin {
+                Some((model.transition_cost(left, node.lcattr) as i64 + cost, None))
+            } else {
+                (0..i)
+                    .filter(|&j| candidates[j].span.end == node.span.begin)
+                    .filter_map(|j| best[j].map(|(c, _)| (c, j)))
+                    .map(|(c, j)| (c + model.transition_cost(candidates[j].rcattr, node.lcattr) as i64 + cost, Some(j)))
+                    .min_by_key(|&(c, _)| c)
+            };
+        }
+
+        let (_, last) = (0..candidates.len())
+            .filter(|&i| candidates[i].span.end == token.span.end)
+            .filter_map(|i| best[i].map(|(c, _)| (c + model.transition_cost(candidates[i].rcattr, right) as i64, i)))
+            .min_by_key(|&(c, _)| c)?;
+
+        let mut path = vec![last];
+        while let Some((_, Some(prev))) = best[*path.last().unwrap()] {
+            path.push(prev);
+        }
+
+        Some(path.into_iter().rev().map(|i| candidates[i].clone()).collect())
+    }
+}
+
+impl Lattice {
+    /// Search-mode tokens of the parsed lattice with the default penalties.
+    pub fn search_tokens(&self, model: &Model) -> Vec<SearchToken> {
+        SearchMode::new().tokens(self, model)
+    }
+}
@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use mecab::{Lattice, Model, MECAB_EOS_NODE};
+use offset::OffsetMap;
+use token::{long_to_i64, Token};
+
+#[cfg(feature = "json")]
+use serde::{Deserialize, Serialize};
+
+/// Alternatives listed per token by `Model::explain`.
+const ALTERNATIVES: usize = 3;
+
+/// Cost breakdown of one token of the best path.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "json", derive(Serialize, Deserialize))]
+pub struct TokenCost {
+    pub token: Token,
+    pub word_cost: i16,
+    /// Connection cost from the previous token, or from BOS.
+    pub connection_cost: i32,
+    /// Cumulative cost of the best path up to and including this token.
+    pub cost: i64,
+    /// Cheapest other nodes starting at the same position.
+    pub alternatives: Vec<Alternative>,
+}
+
+/// Node competing with a token of the best path.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "json", derive(Serialize, Deserialize))]
+pub struct Alternative {
+    pub token: Token,
+    pub word_cost: i16,
+    /// Cost of the cheapest complete path through this node.
+    pub path_cost: i64,
+    /// How much more expensive that path is than the best path.
+    pub delta: i64,
+}
+
+/// Why the best path of a lattice won, see `Explanation::new`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "json", derive(Serialize, Deserialize))]
+pub struct Explanation {
+    pub sentence: String,
+    pub tokens: Vec<TokenCost>,
+    /// Connection cost from the last token to EOS.
+    pub eos_connection_cost: i32,
+    /// Cost of the best path.
+    pub cost: i64,
+}
+
+impl Explanation {
+    /// Breaks down the best path of a parsed lattice and lists up to `alternatives`
+    /// competing nodes per token, cheapest first.
+    pub fn new(lattice: &Lattice, model: &Model, alternatives: usize) -> Explanation {
+        Explanation::with_transition_costs(lattice,
+                                           |rc_attr, lc_attr| model.transition_cost(rc_attr, lc_attr),
+                                           alternatives)
+    }
+
+    fn with_transition_costs<F: Fn(u16, u16) -> i32>(lattice: &Lattice,
+                                                     transition_cost: F,
+                                                     alternatives: usize)
+                                                     -> Explanation {
+        let sentence = lattice.sentence();
+        let offsets = OffsetMap::new(&sentence);
+        let costs = costs_to_eos(lattice, &transition_cost);
+
+        let mut tokens = Vec::new();
+        let mut prev = lattice.bos_node();
+        let mut pos = 0;
+        let mut eos_connection_cost = 0;
+
+        for node in lattice.bos_node().iter_next().skip(1) {
+            let connection_cost = transition_cost(prev.rcattr, node.lcattr);
+            if node.stat as i32 == MECAB_EOS_NODE {
+                eos_connection_cost = connection_cost;
+                break;
+            }
+
+            let cost = long_to_i64(node.cost);
+            let best = cost + costs.get(&node.id).cloned().unwrap_or(0);
+            let mut others: Vec<Alternative> = lattice.begin_nodes(pos)
+                .map_or(Vec::new(), |n| n.iter_bnext().collect())
+                .into_iter()
+                .filter(|other| other.id != node.id)
+                .filter_map(|other| {
+                    let path_cost = long_to_i64(other.cost) + costs.get(&other.id)?;
+                    Some(Alternative {
+                        token: Token::from_node(&other, &offsets),
+                        word_cost: other.wcost,
+                        path_cost,
+                        delta: path_cost - best,
+                    })
+                })
+                .collect();
+            others.sort_by_key(|a| (a.delta, a.token.span.end));
+            others.truncate(alternatives);
+
+            tokens.push(TokenCost {
+                token: Token::from_node(&node, &offsets),
+                word_cost: node.wcost,
+                connection_cost,
+                cost,
+                alternatives: others,
+            });
+            pos += node.rlength as usize;
+            prev = node;
+        }
+
+        Explanation {
+            sentence,
+            tokens,
+            eos_connection_cost,
+            cost: long_to_i64(lattice.eos_node().cost),
+        }
+    }
+}
+
+// cheapest cost from the end of every node to EOS, nodes that cannot reach EOS are left out
+fn costs_to_eos<F: Fn(u16, u16) -> i32>(lattice: &Lattice, transition_cost: &F) -> HashMap<u32, i64> {
+    let mut costs = HashMap::new();
+    for pos in (0..lattice.size() + 1).rev() {
+        for node in lattice.begin_nodes(pos).map_or(Vec::new(), |n| n.iter_bnext().collect()) {
+            if node.stat as i32 == MECAB_EOS_NODE {
+                costs.insert(node.id, 0);
+                continue;
+            }
+
+            let end = pos + node.rlength as usize;
+            let cost = lattice.begin_nodes(end)
+                .map_or(Vec::new(), |n| n.iter_bnext().collect())
+                .iter()
+                .filter_map(|right| {
+                    costs.get(&right.id).map(|cost| {
+                        transition_cost(node.rcattr, right.lcattr) as i64 + right.wcost as i64 + cost
+                    })
+                })
+                .min();
+            if let Some(cost) = cost {
+                costs.insert(node.id, cost);
+            }
+        }
+    }
+    costs
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", self.sentence)?;
+        for t in &self.tokens {
+            writeln!(f,
+                     "{}\t{}\tword {}\tconnection {}\tcost {}",
+                     t.token.surface,
+                     t.token.feature,
+                     t.word_cost,
+                     t.connection_cost,
+                     t.cost)?;
+            for a in &t.alternatives {
+                writeln!(f, "  +{}\t{}\t{}\tword {}", a.delta, a.token.surface, a.token.feature, a.word_cost)?;
+            }
+        }
+        writeln!(f, "EOS\tconnection {}\tcost {}", self.eos_connection_cost, self.cost)
+    }
+}
+
+impl Model {
+    /// Parses `input` and explains its best path, `None` if parsing fails.
+    pub fn explain<T: Into<Vec<u8>>>(&self, input: T) -> Option<Explanation> {
+        let tagger = self.create_tagger();
+        let mut lattice = self.create_lattice();
+        lattice.set_sentence(input);
+        if tagger.parse(&lattice) {
+            Some(Explanation::new(&lattice, self, ALTERNATIVES))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mecab::MockNode;
+    use token::Span;
+
+    fn cost(rc_attr: u16, lc_attr: u16) -> i32 {
+        (rc_attr + lc_attr) as i32
+    }
+
+    // the best path is す|もも, すも|も and す|も|も compete with it
+    fn lattice() -> Lattice {
+        let mut lattice = Lattice::new();
+        lattice.set_sentence("すもも");
+        lattice.mock_nodes(&[MockNode::new(0, 9, "名詞,一般,*,*,*,*,すもも,スモモ,スモモ", 100).attr(1),
+                             MockNode::new(0, 3, "接頭詞,名詞接続,*,*,*,*,す,ス,ス", 10).attr(2),
+                             MockNode::new(0, 6, "名詞,一般,*,*,*,*,すも,スモ,スモ", 30).attr(3),
+                             MockNode::new(3, 6, "名詞,一般,*,*,*,*,もも,モモ,モモ", 10).attr(4),
+                             MockNode::new(3, 3, "助詞,係助詞,*,*,*,*,も,モ,モ", 40).attr(6),
+                             MockNode::new(6, 3, "助詞,係助詞,*,*,*,*,も,モ,モ", 10).attr(5)],
+                           cost);
+        lattice
+    }
+
+    #[test]
+    fn costs_add_up() {
+        let lattice = lattice();
+        let explanation = Explanation::with_transition_costs(&lattice, cost, 3);
+        let surfaces: Vec<&str> = explanation.tokens.iter().map(|t| t.token.surface.as_str()).collect();
+        assert_eq!(surfaces, ["す", "もも"]);
+
+        let mut total = 0;
+        for (t, node) in explanation.tokens.iter().zip(lattice.bos_node().iter_next().skip(1)) {
+            total += t.word_cost as i64 + t.connection_cost as i64;
+            assert_eq!(t.cost, total);
+            assert_eq!(t.cost, long_to_i64(node.cost));
+            assert_eq!(node.path_cost(&node.prev().unwrap()),
+                       Some(t.connection_cost + t.word_cost as i32));
+        }
+        assert_eq!(explanation.tokens[1].connection_cost, 6);
+        assert_eq!(explanation.eos_connection_cost, 4);
+        assert_eq!(explanation.cost, total + explanation.eos_connection_cost as i64);
+        assert_eq!(explanation.cost, 32);
+    }
+
+    #[test]
+    fn alternatives() {
+        let lattice = lattice();
+        let explanation = Explanation::with_transition_costs(&lattice, cost, 3);
+        let alternatives: Vec<(&str, i64, i64)> = explanation.tokens[0]
+            .alternatives
+            .iter()
+            .map(|a| (a.token.surface.as_str(), a.path_cost, a.delta))
+            .collect();
+        assert_eq!(alternatives, [("すも", 56, 24), ("すもも", 102, 70)]);
+        assert_eq!(explanation.tokens[1].alternatives[0].token.span, Span::new(3, 6));
+        assert_eq!(explanation.tokens[1].alternatives[0].delta, 54);
+
+        let explanation = Explanation::with_transition_costs(&lattice, cost, 1);
+        assert_eq!(explanation.tokens[0].alternatives.len(), 1);
+        assert_eq!(explanation.tokens[0].alternatives[0].token.surface, "すも");
+        assert!(Explanation::with_transition_costs(&lattice, cost, 0).tokens.iter().all(|t| t.alternatives.is_empty()));
+    }
+}
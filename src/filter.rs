@@ -0,0 +1,355 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use kana::is_katakana;
+use mecab::{Lattice, Tagger};
+use normalize::Normalizer;
+use token::Token;
+
+/// A step of an `Analyzer`.
+///
+/// Filters rewrite `Token::surface` and leave the spans untouched, so the
+/// original text of a rewritten token is still known.
+pub trait TokenFilter: Send + Sync {
+    /// Rewrites `token` in place, returns `false` to drop it.
+    fn filter(&self, token: &mut Token) -> bool;
+}
+
+impl<F: Fn(&mut Token) -> bool + Send + Sync> TokenFilter for F {
+    fn filter(&self, token: &mut Token) -> bool {
+        self(token)
+    }
+}
+
+/// Drops tokens whose POS matches one of the stop tags.
+#[derive(Clone, Debug, Default)]
+pub struct PosStopFilter {
+    tags: Vec<Vec<String>>,
+}
+
+impl PosStopFilter {
+    pub fn new() -> PosStopFilter {
+        PosStopFilter::default()
+    }
+
+    /// Adds a feature prefix such as `["助詞"]` or `["名詞", "接尾"]`, `*` matches any field.
+    pub fn tag(mut self, pattern: &[&str]) -> PosStopFilter {
+        self.tags.push(pattern.iter().map(|&field| field.to_owned()).collect());
+        self
+    }
+}
+
+impl TokenFilter for PosStopFilter {
+    fn filter(&self, token: &mut Token) -> bool {
+        !self.tags.iter().any(|tag| token.matches_pos(tag))
+    }
+}
+
+/// Drops tokens whose surface is a stopword.
+#[derive(Clone, Debug, Default)]
+pub struct StopwordFilter {
+    words: HashSet<String>,
+}
+
+impl StopwordFilter {
+    pub fn new<T: Into<String>, I: IntoIterator<Item = T>>(words: I) -> StopwordFilter {
+        StopwordFilter { words: words.into_iter().map(Into::into).collect() }
+    }
+}
+
+impl TokenFilter for StopwordFilter {
+    fn filter(&self, token: &mut Token) -> bool {
+        !self.words.contains(&token.surface)
+    }
+}
+
+/// Replaces the surface with the lemma.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BaseFormFilter;
+
+impl TokenFilter for BaseFormFilter {
+    fn filter(&self, token: &mut Token) -> bool {
+        token.surface = token.lemma().to_owned();
+        true
+    }
+}
+
+/// Replaces the surface with the katakana reading or pronunciation if there is one.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ReadingFormFilter {
+    pronunciation: bool,
+}
+
+impl ReadingFormFilter {
+    pub fn new() -> ReadingFormFilter {
+        ReadingFormFilter::default()
+    }
+
+    pub fn pronunciation(mut self, enable: bool) -> ReadingFormFilter {
+        self.pronunciation = enable;
+        self
+    }
+}
+
+impl TokenFilter for ReadingFormFilter {
+    fn filter(&self, token: &mut Token) -> bool {
+        let reading = if self.pronunciation {
+            token.pronunciation()
+        } else {
+            token.reading()
+        };
+        if let Some(reading) = reading.map(str::to_owned) {
+            token.surface = reading;
+        }
+        true
+    }
+}
+
+/// Removes a trailing `ー` from katakana words, so that `コンピューター` matches `コンピュータ`.
+#[derive(Clone, Copy, Debug)]
+pub struct KatakanaStemFilter {
+    min_len: usize,
+}
+
+impl Default for KatakanaStemFilter {
+    fn default() -> KatakanaStemFilter {
+        KatakanaStemFilter { min_len: 4 }
+    }
+}
+
+impl KatakanaStemFilter {
+    pub fn new() -> KatakanaStemFilter {
+        KatakanaStemFilter::default()
+    }
+
+    /// Minimum length in chars, including the mark, of words that are stemmed, 4 by default.
+    pub fn min_len(mut self, min_len: usize) -> KatakanaStemFilter {
+        self.min_len = min_len;
+        self
+    }
+}
+
+impl TokenFilter for KatakanaStemFilter {
+    fn filter(&self, token: &mut Token) -> bool {
+        if token.surface.ends_with('ー') && token.surface.chars().count() >= self.min_len &&
+           token.surface.chars().all(is_katakana) {
+            token.surface.pop();
+        }
+        true
+    }
+}
+
+/// Rewrites kanji and full-width numerals such as `三千五百` or `１２` as ASCII digits.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NumberFilter;
+
+impl TokenFilter for NumberFilter {
+    fn filter(&self, token: &mut Token) -> bool {
+        if let Some(number) = parse_number(&token.surface) {
+            token.surface = number.to_string();
+        }
+        true
+    }
+}
+
+/// Folds full-width ASCII to ASCII and half-width katakana to full-width katakana.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WidthFilter;
+
+impl TokenFilter for WidthFilter {
+    fn filter(&self, token: &mut Token) -> bool {
+        token.surface = Normalizer::new().fold_width(true).normalize(&token.surface).text;
+        true
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LowercaseFilter;
+
+impl TokenFilter for LowercaseFilter {
+    fn filter(&self, token: &mut Token) -> bool {
+        token.surface.make_ascii_lowercase();
+        true
+    }
+}
+
+/// A chain of token filters applied in order.
+#[derive(Clone, Default)]
+pub struct Analyzer {
+    filters: Vec<Arc<dyn TokenFilter>>,
+}
+
+impl Analyzer {
+    pub fn new() -> Analyzer {
+        Analyzer::default()
+    }
+
+    pub fn filter<F: TokenFilter + 'static>(mut self, filter: F) -> Analyzer {
+        self.filters.push(Arc::new(filter));
+        self
+    }
+
+    pub fn apply(&self, tokens: Vec<Token>) -> Vec<Token> {
+        tokens.into_iter()
+            .filter_map(|mut token| {
+                if self.filters.iter().all(|f| f.filter(&mut token)) {
+                    Some(token)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Filters the tokens of the current path of `lattice`.
+    pub fn analyze_lattice(&self, lattice: &Lattice) -> Vec<Token> {
+        self.apply(lattice.tokens())
+    }
+
+    pub fn analyze<T: Into<Vec<u8>>>(&self, tagger: &mut Tagger, input: T) -> Vec<Token> {
+        self.apply(tagger.parse_to_tokens(input))
+    }
+}
+
+// value of a numeral written in digits, kanji digits and kanji units
+fn parse_number(text: &str) -> Option<u64> {
+    if text.is_empty() {
+        return None;
+    }
+
+    let mut total: u64 = 0;
+    // value below 万 and the digits read since the last unit
+    let mut section: u64 = 0;
+    let mut digits: Option<u64> = None;
+
+    for c in text.chars() {
+        if let Some(d) = digit(c) {
+            digits = Some(digits.unwrap_or(0).checked_mul(10)?.checked_add(d)?);
+            continue;
+        }
+        match c {
+            '十' | '百' | '千' => {
+                let unit = match c {
+                    '十' => 10,
+                    '百' => 100,
+                    _ => 1000,
+                };
+                section = section.checked_add(digits.take().unwrap_or(1).checked_mul(unit)?)?;
+            }
+            '万' | '億' | '兆' => {
+                let unit: u64 = match c {
+                    '万' => 10_000,
+                    '億' => 100_000_000,
+                    _ => 1_000_000_000_000,
+                };
+                let value = section.checked_add(digits.take().unwrap_or(0))?;
+                total = total.checked_add(value.max(1).checked_mul(unit)?)?;
+                section = 0;
+            }
+            _ => return None,
+        }
+    }
+
+    total.checked_add(section)?.checked_add(digits.unwrap_or(0))
+}
+
+fn digit(c: char) -> Option<u64> {
+    match c {
+        '0'..='9' => Some(c as u64 - '0' as u64),
+        '０'..='９' => Some(c as u64 - '０' as u64),
+        '〇' | '零' => Some(0),
+        '一' => Some(1),
+        '二' => Some(2),
+        '三' => Some(3),
+        '四' => Some(4),
+        '五' => Some(5),
+        '六' => Some(6),
+        '七' => Some(7),
+        '八' => Some(8),
+        '九' => Some(9),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use format::{read_sentences, OutputFormat};
+
+    fn tokens(lines: &[(&str, &str)]) -> Vec<Token> {
+        let text: String = lines.iter().map(|&(surface, feature)| format!("{}\t{}\n", surface, feature)).collect();
+        read_sentences(&(text + "EOS\n"), OutputFormat::Default).unwrap().remove(0)
+    }
+
+    fn surfaces(tokens: &[Token]) -> Vec<&str> {
+        tokens.iter().map(|t| t.surface.as_str()).collect()
+    }
+
+    fn sentence() -> Vec<Token> {
+        tokens(&[("コンピューター", "名詞,一般,*,*,*,*,コンピューター,コンピューター,コンピューター"),
+                 ("を", "助詞,格助詞,一般,*,*,*,を,ヲ,ヲ"),
+                 ("使っ", "動詞,自立,*,*,五段・ワ行促音便,連用タ接続,使う,ツカッ,ツカッ"),
+                 ("た", "助動詞,*,*,*,特殊・タ,基本形,た,タ,タ")])
+    }
+
+    #[test]
+    fn stop_filters() {
+        let analyzer = Analyzer::new()
+            .filter(PosStopFilter::new().tag(&["助詞"]))
+            .filter(StopwordFilter::new(vec!["た"]));
+        let tokens = analyzer.apply(sentence());
+        assert_eq!(surfaces(&tokens), ["コンピューター", "使っ"]);
+        // dropping tokens keeps the spans of the others
+        assert_eq!(tokens[1].span.begin, "コンピューターを".len());
+    }
+
+    #[test]
+    fn base_and_reading_forms() {
+        assert_eq!(surfaces(&Analyzer::new().filter(BaseFormFilter).apply(sentence())),
+                   ["コンピューター", "を", "使う", "た"]);
+        assert_eq!(surfaces(&Analyzer::new().filter(ReadingFormFilter::new()).apply(sentence())),
+                   ["コンピューター", "ヲ", "ツカッ", "タ"]);
+
+        let tokens = tokens(&[("東京", "名詞,固有名詞,地域,一般,*,*,東京,トウキョウ,トーキョー")]);
+        let analyzer = Analyzer::new().filter(ReadingFormFilter::new().pronunciation(true));
+        assert_eq!(surfaces(&analyzer.apply(tokens)), ["トーキョー"]);
+    }
+
+    #[test]
+    fn katakana_stem() {
+        let analyzer = Analyzer::new().filter(BaseFormFilter).filter(KatakanaStemFilter::new());
+        assert_eq!(analyzer.apply(sentence())[0].surface, "コンピュータ");
+
+        let tokens = tokens(&[("カー", "名詞,一般,*,*,*,*,カー,カー,カー"), ("ひー", "名詞,一般,*,*,*,*,*")]);
+        assert_eq!(surfaces(&Analyzer::new().filter(KatakanaStemFilter::new()).apply(tokens.clone())),
+                   ["カー", "ひー"]);
+        assert_eq!(surfaces(&Analyzer::new().filter(KatakanaStemFilter::new().min_len(2)).apply(tokens)),
+                   ["カ", "ひー"]);
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(parse_number("三千五百"), Some(3500));
+        assert_eq!(parse_number("１２"), Some(12));
+        assert_eq!(parse_number("二〇二四"), Some(2024));
+        assert_eq!(parse_number("十"), Some(10));
+        assert_eq!(parse_number("一億二千万"), Some(120_000_000));
+        assert_eq!(parse_number("3万"), Some(30_000));
+        assert_eq!(parse_number("三つ"), None);
+        assert_eq!(parse_number(""), None);
+        assert_eq!(parse_number("99999999999999999999"), None);
+    }
+
+    #[test]
+    fn width_and_case() {
+        let tokens = tokens(&[("ＡＢＣ", "名詞,固有名詞,組織,*,*,*,*"), ("ｶﾞｽ", "名詞,一般,*,*,*,*,*")]);
+        let analyzer = Analyzer::new().filter(WidthFilter).filter(LowercaseFilter).filter(NumberFilter);
+        assert_eq!(surfaces(&analyzer.apply(tokens)), ["abc", "ガス"]);
+    }
+
+    #[test]
+    fn closures() {
+        let analyzer = Analyzer::new().filter(|token: &mut Token| token.surface != "を");
+        assert_eq!(surfaces(&analyzer.apply(sentence())), ["コンピューター", "使っ", "た"]);
+    }
+}
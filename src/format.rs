@@ -0,0 +1,319 @@
+use std::error::Error;
+use std::fmt;
+
+use mecab::{Lattice, MECAB_NOR_NODE};
+use token::{Span, Token};
+
+/// Text formats produced by mecab's `-O` option with the IPADIC `dicrc`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// `surface\tfeature` per token and `EOS` after each sentence.
+    Default,
+    /// `surface\treading\tbase\tPOS-POS\tctype\tcform` per token and `EOS` after each sentence.
+    Chasen,
+    /// Space terminated surfaces, one sentence per line.
+    Wakati,
+    /// Concatenated readings, one sentence per line.
+    ///
+    /// The format carries no token boundaries, so each line is read as a
+    /// single token whose surface is the reading.
+    Yomi,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FormatError {
+    /// 1-based line number of the offending line.
+    pub line: usize,
+    pub message: String,
+}
+
+impl FormatError {
+    pub(crate) fn new<T: Into<String>>(line: usize, message: T) -> FormatError {
+        FormatError {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for FormatError {}
+
+const EOS: &str = "EOS";
+
+/// Reads every sentence of mecab output in the given format.
+pub fn read_sentences(text: &str, format: OutputFormat) -> Result<Vec<Vec<Token>>, FormatError> {
+    match format {
+        OutputFormat::Default | OutputFormat::Chasen => read_eos_terminated(text, format),
+        OutputFormat::Wakati => {
+            Ok(text.lines()
+                .map(|line| {
+                    let mut surfaces: Vec<&str> = line.split(' ').collect();
+                    if surfaces.last() == Some(&"") {
+                        surfaces.pop();
+                    }
+                    build_sentence(surfaces.into_iter().map(|s| (s.to_owned(), String::new())))
+                })
+                .collect())
+        }
+        OutputFormat::Yomi => {
+            Ok(text.lines()
+                .map(|line| build_sentence(Some((line.to_owned(), String::new())).into_iter()))
+                .collect())
+        }
+    }
+}
+
+/// Reads `-N n` output in the default format, grouping the paths of each sentence.
+///
+/// Consecutive paths of the same sentence form a group of at most `n` paths,
+/// sentences with fewer than `n` segmentations get smaller groups.
+pub fn read_nbest(text: &str, n: usize) -> Result<Vec<Vec<Vec<Token>>>, FormatError> {
+    if n == 0 {
+        return Err(FormatError::new(1, "n must be at least 1"));
+    }
+
+    let mut blocks: Vec<Vec<Vec<Token>>> = Vec::new();
+    for path in read_sentences(text, OutputFormat::Default)? {
+        let same_sentence = blocks.last()
+            .is_some_and(|block| block.len() < n && concat_surfaces(&block[0]) == concat_surfaces(&path));
+        if same_sentence {
+            blocks.last_mut().unwrap().push(path);
+        } else {
+            blocks.push(vec![path]);
+        }
+    }
+
+    Ok(blocks)
+}
+
+/// Line break of mecab output, `\r\n` when it was written on Windows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    /// Line break used by the first line of `text`, `Lf` if there is none.
+    pub fn detect(text: &str) -> LineEnding {
+        match text.find('\n') {
+            Some(i) if text[..i].ends_with('\r') => LineEnding::CrLf,
+            _ => LineEnding::Lf,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
+
+/// Renders sentences so that output read with `read_sentences` is reproduced byte for byte.
+///
+/// Lines end with `\n`, output with `\r\n` line breaks is reproduced by `write_sentences_with`.
+pub fn write_sentences(sentences: &[Vec<Token>], format: OutputFormat) -> String {
+    write_sentences_with(sentences, format, LineEnding::Lf)
+}
+
+/// Like `write_sentences` with the given line break, see `LineEnding::detect`.
+pub fn write_sentences_with(sentences: &[Vec<Token>], format: OutputFormat, line_ending: LineEnding) -> String {
+    let newline = line_ending.as_str();
+    let mut out = String::new();
+
+    for tokens in sentences {
+        for token in tokens {
+            match format {
+                OutputFormat::Default => {
+                    out.push_str(&token.surface);
+                    out.push('\t');
+                    out.push_str(&token.feature);
+                    out.push_str(newline);
+                }
+                OutputFormat::Chasen => {
+                    let fields = token.features();
+                    let field = |i: usize| match fields.get(i) {
+                        Some(&"*") | None => "",
+                        Some(f) => f,
+                    };
+                    let pos: Vec<&str> = (0..4).map(field).filter(|f| !f.is_empty()).collect();
+
+                    out.push_str(&[&token.surface[..], field(7), field(6), &pos.join("-"), field(4), field(5)]
+                        .join("\t"));
+                    out.push_str(newline);
+                }
+                OutputFormat::Wakati => {
+                    out.push_str(&token.surface);
+                    out.push(' ');
+                }
+                OutputFormat::Yomi => {
+                    match token.feature_at(7) {
+                        Some(reading) if reading != "*" => out.push_str(reading),
+                        _ => out.push_str(&token.surface),
+                    }
+                }
+            }
+        }
+
+        match format {
+            OutputFormat::Default | OutputFormat::Chasen => {
+                out.push_str(EOS);
+                out.push_str(newline);
+            }
+            OutputFormat::Wakati | OutputFormat::Yomi => out.push_str(newline),
+        }
+    }
+
+    out
+}
+
+impl Lattice {
+    /// Loads a finished analysis into the lattice through `set_result`.
+    pub fn set_result_tokens(&self, tokens: &[Token]) {
+        self.set_result(write_sentences(&[tokens.to_vec()], OutputFormat::Default));
+    }
+}
+
+fn read_eos_terminated(text: &str, format: OutputFormat) -> Result<Vec<Vec<Token>>, FormatError> {
+    let mut sentences = Vec::new();
+    let mut current = Vec::new();
+    let mut line_no = 0;
+
+    for line in text.lines() {
+        line_no += 1;
+        if line == EOS {
+            sentences.push(build_sentence(current.drain(..)));
+            continue;
+        }
+
+        let columns: Vec<&str> = line.split('\t').collect();
+        let token = match format {
+            OutputFormat::Default => {
+                if columns.len() != 2 {
+                    return Err(FormatError::new(line_no, "expected surface and feature separated by a tab"));
+                }
+                (columns[0].to_owned(), columns[1].to_owned())
+            }
+            _ => {
+                if columns.len() != 6 {
+                    return Err(FormatError::new(line_no, "expected 6 tab separated chasen columns"));
+                }
+                (columns[0].to_owned(), chasen_feature(&columns))
+            }
+        };
+        current.push(token);
+    }
+
+    if !current.is_empty() {
+        return Err(FormatError::new(line_no, "missing EOS after the last sentence"));
+    }
+
+    Ok(sentences)
+}
+
+// rebuilds an IPADIC feature from the chasen columns, empty columns become `*`
+fn chasen_feature(columns: &[&str]) -> String {
+    let star = |s: &str| if s.is_empty() { "*".to_owned() } else { s.to_owned() };
+    let mut fields: Vec<String> = columns[3].split('-').filter(|s| !s.is_empty()).map(str::to_owned).collect();
+    fields.resize(4, "*".to_owned());
+    fields.push(star(columns[4]));
+    fields.push(star(columns[5]));
+    fields.push(star(columns[2]));
+    fields.push(star(columns[1]));
+
+    fields.join(",")
+}
+
+fn build_sentence<I: Iterator<Item = (String, String)>>(tokens: I) -> Vec<Token> {
+    let mut offset = (0, 0, 0);
+    tokens.map(|(surface, feature)| {
+            let (bytes, chars, units) = offset;
+            offset = (bytes + surface.len(),
+                      chars + surface.chars().count(),
+                      units + surface.encode_utf16().count());
+            Token {
+                span: Span::new(bytes, offset.0),
+                char_span: Span::new(chars, offset.1),
+                utf16_span: Span::new(units, offset.2),
+                surface,
+                feature,
+                stat: MECAB_NOR_NODE as u8,
+                posid: 0,
+                lcattr: 0,
+                rcattr: 0,
+                wcost: 0,
+                cost: 0,
+                prob: 0.0,
+            }
+        })
+        .collect()
+}
+
+fn concat_surfaces(tokens: &[Token]) -> String {
+    tokens.iter().map(|t| &t.surface[..]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NBEST: &str = "すもも\t名詞,一般,*,*,*,*,すもも,スモモ,スモモ\n\
+                         も\t助詞,係助詞,*,*,*,*,も,モ,モ\n\
+                         EOS\n\
+                         す\t接頭詞,名詞接続,*,*,*,*,す,ス,ス\n\
+                         もも\t名詞,一般,*,*,*,*,もも,モモ,モモ\n\
+                         も\t助詞,係助詞,*,*,*,*,も,モ,モ\n\
+                         EOS\n\
+                         うち\t名詞,非自立,副詞可能,*,*,*,うち,ウチ,ウチ\n\
+                         EOS\n";
+
+    fn surfaces(tokens: &[Token]) -> Vec<&str> {
+        tokens.iter().map(|t| t.surface.as_str()).collect()
+    }
+
+    #[test]
+    fn read_nbest_groups_by_sentence() {
+        let blocks = read_nbest(NBEST, 2).unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].len(), 2);
+        assert_eq!(surfaces(&blocks[0][1]), ["す", "もも", "も"]);
+        assert_eq!(blocks[1].len(), 1);
+        assert_eq!(surfaces(&blocks[1][0]), ["うち"]);
+
+        // a group never holds more than n paths
+        assert_eq!(read_nbest(NBEST, 1).unwrap().len(), 3);
+        assert!(read_nbest(NBEST, 0).is_err());
+    }
+
+    #[test]
+    fn read_nbest_round_trip() {
+        let paths: Vec<Vec<Token>> = read_nbest(NBEST, 2).unwrap().concat();
+        assert_eq!(write_sentences(&paths, OutputFormat::Default), NBEST);
+    }
+
+    #[test]
+    fn crlf_round_trip() {
+        let crlf = NBEST.replace('\n', "\r\n");
+        assert_eq!(LineEnding::detect(&crlf), LineEnding::CrLf);
+        assert_eq!(LineEnding::detect(NBEST), LineEnding::Lf);
+
+        let sentences = read_sentences(&crlf, OutputFormat::Default).unwrap();
+        assert_eq!(sentences[0][0].feature, "名詞,一般,*,*,*,*,すもも,スモモ,スモモ");
+        assert_eq!(write_sentences_with(&sentences, OutputFormat::Default, LineEnding::CrLf), crlf);
+    }
+
+    #[test]
+    fn wakati_round_trip() {
+        let text = "すもも も もも も \r\nうち \r\n";
+        let sentences = read_sentences(text, OutputFormat::Wakati).unwrap();
+        assert_eq!(surfaces(&sentences[0]), ["すもも", "も", "もも", "も"]);
+        assert_eq!(write_sentences_with(&sentences, OutputFormat::Wakati, LineEnding::detect(text)), text);
+    }
+}
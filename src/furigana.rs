@@ -0,0 +1,239 @@
+use kana::{is_kana, is_kanji, katakana_to_hiragana};
+use token::{Span, Token};
+
+/// Part of a sentence with the reading to show above it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Ruby {
+    pub base: String,
+    /// Hiragana reading, `None` for text that needs no annotation.
+    pub reading: Option<String>,
+    /// Byte span of `base` within the sentence.
+    pub span: Span,
+}
+
+type ReadingFallback = Box<dyn Fn(&Token) -> Option<String> + Send + Sync>;
+
+/// Splits token readings over their kanji and okurigana.
+#[derive(Default)]
+pub struct Furigana {
+    fallback: Option<ReadingFallback>,
+}
+
+impl Furigana {
+    pub fn new() -> Furigana {
+        Furigana::default()
+    }
+
+    /// Provides a katakana or hiragana reading for tokens whose feature has none.
+    pub fn fallback<F: Fn(&Token) -> Option<String> + Send + Sync + 'static>(mut self, fallback: F) -> Furigana {
+        self.fallback = Some(Box::new(fallback));
+        self
+    }
+
+    pub fn annotate(&self, tokens: &[Token]) -> Vec<Ruby> {
+        tokens.iter().flat_map(|token| self.annotate_token(token)).collect()
+    }
+
+    pub fn annotate_token(&self, token: &Token) -> Vec<Ruby> {
+        let plain = || {
+            vec![Ruby {
+                     base: token.surface.clone(),
+                     reading: None,
+                     span: token.span,
+                 }]
+        };
+
+        if !token.surface.chars().any(|c| !is_kana(c) || is_kanji(c)) {
+            return plain();
+        }
+        let reading = match token.reading().map(str::to_owned) {
+            Some(reading) => reading,
+            None => {
+                match self.fallback.as_ref().and_then(|f| f(token)) {
+                    Some(reading) => reading,
+                    None => return plain(),
+                }
+            }
+        };
+        let reading = katakana_to_hiragana(&reading);
+        if katakana_to_hiragana(&token.surface) == reading {
+            return plain();
+        }
+
+        let segments = segments(&token.surface);
+        let readings: Vec<char> = reading.chars().collect();
+        let mut rubies = Vec::new();
+
+        match align(&segments, &readings) {
+            Some(aligned) => {
+                for (&(offset, ref base, kana), reading) in segments.iter().zip(aligned) {
+                    rubies.push(Ruby {
+                        base: base.clone(),
+                        reading: if kana { None } else { Some(reading) },
+                        span: Span::new(token.span.begin + offset, token.span.begin + offset + base.len()),
+                    });
+                }
+            }
+            // the reading does not fit the okurigana, annotate the whole token
+            None => {
+                rubies.push(Ruby {
+                    base: token.surface.clone(),
+                    reading: Some(reading),
+                    span: token.span,
+                })
+            }
+        }
+
+        rubies
+    }
+}
+
+/// Renders rubies as HTML `<ruby>` markup.
+pub fn ruby_html(rubies: &[Ruby]) -> String {
+    let mut html = String::new();
+    for ruby in rubies {
+        match ruby.reading {
+            Some(ref reading) => {
+                html.push_str("<ruby>");
+                html.push_str(&escape_html(&ruby.base));
+                html.push_str("<rt>");
+                html.push_str(&escape_html(reading));
+                html.push_str("</rt></ruby>");
+            }
+            None => html.push_str(&escape_html(&ruby.base)),
+        }
+    }
+    html
+}
+
+// byte offset, text and kana flag of alternating kana and non-kana runs
+fn segments(surface: &str) -> Vec<(usize, String, bool)> {
+    let mut segments: Vec<(usize, String, bool)> = Vec::new();
+    for (i, c) in surface.char_indices() {
+        let kana = is_kana(c) && !is_kanji(c);
+        match segments.last_mut() {
+            Some(&mut (_, ref mut text, k)) if k == kana => text.push(c),
+            _ => segments.push((i, c.to_string(), kana)),
+        }
+    }
+    segments
+}
+
+// assigns a part of the reading to every segment, kana segments have to match literally
+fn align(segments: &[(usize, String, bool)], reading: &[char]) -> Option<Vec<String>> {
+    let (&(_, ref text, kana), rest) = match segments.split_first() {
+        Some(first) => first,
+        None => return if reading.is_empty() { Some(Vec::new()) } else { None },
+    };
+
+    if kana {
+        let kana: Vec<char> = katakana_to_hiragana(text).chars().collect();
+        if !reading.starts_with(&kana) {
+            return None;
+        }
+        return align(rest, &reading[kana.len()..]).map(|mut aligned| {
+            aligned.insert(0, text.clone());
+            aligned
+        });
+    }
+
+    // non-kana runs need at least one reading char, the rest has to fit the following segments
+    for len in 1..reading.len() + 1 {
+        if let Some(mut aligned) = align(rest, &reading[len..]) {
+            aligned.insert(0, reading[..len].iter().collect());
+            return Some(aligned);
+        }
+    }
+    None
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mecab::MECAB_NOR_NODE;
+
+    fn token(surface: &str, feature: &str) -> Token {
+        Token {
+            surface: surface.to_owned(),
+            feature: feature.to_owned(),
+            span: Span::new(0, surface.len()),
+            char_span: Span::new(0, surface.chars().count()),
+            utf16_span: Span::new(0, surface.encode_utf16().count()),
+            stat: MECAB_NOR_NODE as u8,
+            posid: 0,
+            lcattr: 0,
+            rcattr: 0,
+            wcost: 0,
+            cost: 0,
+            prob: 0.0,
+        }
+    }
+
+    fn rubies(rubies: &[Ruby]) -> Vec<(&str, Option<&str>)> {
+        rubies.iter().map(|r| (r.base.as_str(), r.reading.as_deref())).collect()
+    }
+
+    #[test]
+    fn okurigana() {
+        let furigana = Furigana::new();
+        let motsu = furigana.annotate_token(&token("持っ", "動詞,自立,*,*,五段・タ行,連用タ接続,持つ,モッ,モッ"));
+        assert_eq!(rubies(&motsu), [("持", Some("も")), ("っ", None)]);
+        assert_eq!(motsu[1].span, Span::new(3, 6));
+
+        let ocha = furigana.annotate_token(&token("お茶", "名詞,一般,*,*,*,*,お茶,オチャ,オチャ"));
+        assert_eq!(rubies(&ocha), [("お", None), ("茶", Some("ちゃ"))]);
+
+        let kakikomu = furigana.annotate_token(&token("書き込む", "動詞,自立,*,*,五段・マ行,基本形,書き込む,カキコム,カキコム"));
+        assert_eq!(rubies(&kakikomu), [("書", Some("か")), ("き", None), ("込", Some("こ")), ("む", None)]);
+    }
+
+    #[test]
+    fn reading_that_does_not_fit() {
+        let rubies = Furigana::new().annotate_token(&token("持っ", "動詞,自立,*,*,*,*,持つ,モチ,モチ"));
+        assert_eq!(rubies, [Ruby {
+                               base: "持っ".to_owned(),
+                               reading: Some("もち".to_owned()),
+                               span: Span::new(0, 6),
+                           }]);
+    }
+
+    #[test]
+    fn kana_needs_no_ruby() {
+        let furigana = Furigana::new();
+        assert_eq!(rubies(&furigana.annotate_token(&token("すもも", "名詞,一般,*,*,*,*,すもも,スモモ,スモモ"))),
+                   [("すもも", None)]);
+        assert_eq!(rubies(&furigana.annotate_token(&token("テレビ", "名詞,一般,*,*,*,*,テレビ,テレビ,テレビ"))),
+                   [("テレビ", None)]);
+    }
+
+    #[test]
+    fn fallback() {
+        let unknown = token("鬱", "名詞,一般,*,*,*,*,*");
+        assert_eq!(rubies(&Furigana::new().annotate_token(&unknown)), [("鬱", None)]);
+
+        let furigana = Furigana::new().fallback(|token| if token.surface == "鬱" {
+            Some("ウツ".to_owned())
+        } else {
+            None
+        });
+        assert_eq!(rubies(&furigana.annotate_token(&unknown)), [("鬱", Some("うつ"))]);
+        // the fallback is only asked for tokens without a reading
+        assert_eq!(rubies(&furigana.annotate_token(&token("本", "名詞,一般,*,*,*,*,本,ホン,ホン"))),
+                   [("本", Some("ほん"))]);
+    }
+
+    #[test]
+    fn html() {
+        let mut tokens = vec![token("<", "記号,括弧開,*,*,*,*,<,<,<"),
+                              token("持っ", "動詞,自立,*,*,五段・タ行,連用タ接続,持つ,モッ,モッ"),
+                              token("&\"", "記号,一般,*,*,*,*,*")];
+        tokens[1].span = Span::new(1, 7);
+        let rubies = Furigana::new().annotate(&tokens);
+        assert_eq!(rubies[2].span, Span::new(4, 7));
+        assert_eq!(ruby_html(&rubies), "&lt;<ruby>持<rt>も</rt></ruby>っ&amp;&quot;");
+    }
+}
@@ -0,0 +1,291 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use mecab::{Lattice, Model, Node, MECAB_BOS_NODE, MECAB_EOS_NODE, MECAB_MARGINAL_PROB};
+use offset::OffsetMap;
+use token::{long_to_i64, Span, Token};
+
+#[cfg(feature = "json")]
+use serde::{Deserialize, Serialize};
+
+/// Candidate node of an exported lattice.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "json", derive(Serialize, Deserialize))]
+pub struct GraphNode {
+    pub id: usize,
+    /// Byte span of the surface, BOS and EOS have empty spans at the sentence edges.
+    pub span: Span,
+    pub surface: String,
+    pub feature: String,
+    pub stat: u8,
+    pub lcattr: u16,
+    pub rcattr: u16,
+    pub posid: u16,
+    pub wcost: i16,
+    pub cost: i64,
+    pub is_best: bool,
+    pub prob: Option<f32>,
+}
+
+/// Connection between two adjacent candidate nodes.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "json", derive(Serialize, Deserialize))]
+pub struct GraphEdge {
+    pub from: usize,
+    pub to: usize,
+    /// Connection cost from `Model::transition_cost`, excluding the word cost of `to`.
+    pub cost: i32,
+}
+
+/// Snapshot of a parsed lattice that does not depend on libmecab.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "json", derive(Serialize, Deserialize))]
+pub struct LatticeGraph {
+    pub sentence: String,
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+    /// Node ids of the best path from BOS to EOS.
+    pub best_path: Vec<usize>,
+    pub z: Option<f64>,
+}
+
+impl LatticeGraph {
+    pub fn new(lattice: &Lattice, model: &Model) -> LatticeGraph {
+        LatticeGraph::with_transition_costs(lattice, |rc_attr, lc_attr| model.transition_cost(rc_attr, lc_attr))
+    }
+
+    fn with_transition_costs<F: Fn(u16, u16) -> i32>(lattice: &Lattice, transition_cost: F) -> LatticeGraph {
+        let len = lattice.size();
+        let sentence = lattice.sentence();
+        let offsets = OffsetMap::new(&sentence);
+        let marginal = lattice.has_request_type(MECAB_MARGINAL_PROB);
+        let mut nodes = Vec::new();
+        let mut ids = HashMap::new();
+        let mut edges = Vec::new();
+
+        for pos in 0..len + 1 {
+            let right: Vec<Node> = lattice.begin_nodes(pos).map_or(Vec::new(), |n| n.iter_bnext().collect());
+            let left: Vec<Node> = lattice.end_nodes(pos).map_or(Vec::new(), |n| n.iter_enext().collect());
+
+            for node in left.iter().chain(right.iter()) {
+                let id = nodes.len();
+                if *ids.entry(node.id).or_insert(id) == id {
+                    nodes.push(graph_node(id, node, &offsets, marginal));
+                }
+            }
+            for l in &left {
+                for r in &right {
+                    edges.push(GraphEdge {
+                        from: ids[&l.id],
+                        to: ids[&r.id],
+                        cost: transition_cost(l.rcattr, r.lcattr),
+                    });
+                }
+            }
+        }
+
+        let best_path = lattice.bos_node()
+            .iter_next()
+            .filter_map(|node| ids.get(&node.id).cloned())
+            .collect();
+
+        LatticeGraph {
+            sentence,
+            nodes,
+            edges,
+            best_path,
+            z: if marginal { Some(lattice.z()) } else { None },
+        }
+    }
+
+    pub fn node(&self, id: usize) -> Option<&GraphNode> {
+        self.nodes.get(id)
+    }
+
+    pub fn outgoing(&self, id: usize) -> Vec<&GraphEdge> {
+        self.edges.iter().filter(|e| e.from == id).collect()
+    }
+
+    pub fn incoming(&self, id: usize) -> Vec<&GraphEdge> {
+        self.edges.iter().filter(|e| e.to == id).collect()
+    }
+
+    /// Nodes whose surface starts at byte offset `pos`.
+    pub fn nodes_at(&self, pos: usize) -> Vec<&GraphNode> {
+        self.nodes.iter().filter(|n| n.span.begin == pos).collect()
+    }
+
+    fn is_best_edge(&self, edge: &GraphEdge) -> bool {
+        self.best_path.windows(2).any(|w| w[0] == edge.from && w[1] == edge.to)
+    }
+
+    /// Renders the lattice as a Graphviz digraph with the best path in red.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph lattice {\n  rankdir=LR;\n  node [shape=box];\n");
+
+        for node in &self.nodes {
+            let mut label = match node.stat as i32 {
+                MECAB_BOS_NODE => "BOS".to_owned(),
+                MECAB_EOS_NODE => "EOS".to_owned(),
+                _ => format!("{}\\n{}", escape(&node.surface), escape(&node.feature)),
+            };
+            label.push_str(&format!("\\n[{}, {}) wcost={}", node.span.begin, node.span.end, node.wcost));
+            if let Some(prob) = node.prob {
+                label.push_str(&format!(" prob={:.4}", prob));
+            }
+            let style = if self.best_path.contains(&node.id) {
+                ", color=red, penwidth=2"
+            } else {
+                ""
+            };
+            let _ = writeln!(dot, "  n{} [label=\"{}\"{}];", node.id, label, style);
+        }
+
+        for edge in &self.edges {
+            let style = if self.is_best_edge(edge) {
+                ", color=red, penwidth=2"
+            } else {
+                ""
+            };
+            let _ = writeln!(dot, "  n{} -> n{} [label=\"{}\"{}];", edge.from, edge.to, edge.cost, style);
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> String {
+        ::serde_json::to_string(self).unwrap()
+    }
+
+    #[cfg(feature = "json")]
+    pub fn from_json(json: &str) -> Result<LatticeGraph, ::serde_json::Error> {
+        ::serde_json::from_str(json)
+    }
+}
+
+impl Lattice {
+    pub fn to_graph(&self, model: &Model) -> LatticeGraph {
+        LatticeGraph::new(self, model)
+    }
+}
+
+fn graph_node(id: usize, node: &Node, offsets: &OffsetMap, marginal: bool) -> GraphNode {
+    // BOS and EOS surfaces do not point into the sentence
+    let (span, surface) = match node.stat as i32 {
+        MECAB_BOS_NODE => (Span::new(0, 0), String::new()),
+        MECAB_EOS_NODE => (Span::new(offsets.byte_len(), offsets.byte_len()), String::new()),
+        _ => {
+            let token = Token::from_node(node, offsets);
+            (token.span, token.surface)
+        }
+    };
+
+    GraphNode {
+        id,
+        span,
+        surface,
+        feature: node.feature.clone(),
+        stat: node.stat,
+        lcattr: node.lcattr,
+        rcattr: node.rcattr,
+        posid: node.posid,
+        wcost: node.wcost,
+        cost: long_to_i64(node.cost),
+        is_best: node.isbest,
+        prob: if marginal { Some(node.prob) } else { None },
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mecab::{MockNode, MECAB_NOR_NODE};
+
+    // a"b as one noun or as `a` and `"b`, BOS and EOS have context id 0
+    fn graph(marginal: bool) -> LatticeGraph {
+        let mut lattice = Lattice::new();
+        if marginal {
+            lattice.add_request_type(MECAB_MARGINAL_PROB);
+        }
+        lattice.set_sentence("a\"b");
+        let cost = |rc_attr: u16, lc_attr: u16| rc_attr as i32 * 10 + lc_attr as i32;
+        lattice.mock_nodes(&[MockNode::new(0, 3, "名詞,固有名詞,*,*", 100).attr(3).prob(0.25),
+                             MockNode::new(0, 1, "名詞,一般,*,*", 10).attr(1).prob(0.75),
+                             MockNode::new(1, 2, "記号,\"括弧\\開\",*,*", 10).attr(2).prob(0.75)],
+                           cost);
+        lattice.set_z(-1.5);
+        LatticeGraph::with_transition_costs(&lattice, cost)
+    }
+
+    fn edges(edges: &[&GraphEdge]) -> Vec<(usize, usize, i32)> {
+        edges.iter().map(|e| (e.from, e.to, e.cost)).collect()
+    }
+
+    #[test]
+    fn nodes_and_edges() {
+        let graph = graph(false);
+        assert_eq!(graph.sentence, "a\"b");
+        let surfaces: Vec<&str> = graph.nodes.iter().map(|n| n.surface.as_str()).collect();
+        assert_eq!(surfaces, ["", "a\"b", "a", "\"b", ""]);
+        assert_eq!(graph.nodes[4].stat as i32, MECAB_EOS_NODE);
+        assert_eq!(graph.nodes[4].span, Span::new(3, 3));
+        assert_eq!(graph.nodes[3].span, Span::new(1, 3));
+        assert_eq!(graph.nodes[3].stat as i32, MECAB_NOR_NODE);
+        assert_eq!(graph.nodes[2].cost, 11);
+        assert_eq!(graph.best_path, [0, 2, 3, 4]);
+        assert!(graph.nodes[2].is_best && !graph.nodes[1].is_best);
+        assert_eq!(graph.z, None);
+        assert_eq!(graph.nodes[1].prob, None);
+
+        let ids = |nodes: Vec<&GraphNode>| nodes.iter().map(|n| n.id).collect::<Vec<_>>();
+        assert_eq!(ids(graph.nodes_at(0)), [0, 1, 2]);
+        assert_eq!(ids(graph.nodes_at(1)), [3]);
+        assert!(graph.nodes_at(2).is_empty());
+
+        assert_eq!(edges(&graph.outgoing(0)), [(0, 1, 3), (0, 2, 1)]);
+        assert_eq!(edges(&graph.outgoing(2)), [(2, 3, 12)]);
+        assert_eq!(edges(&graph.incoming(4)), [(1, 4, 30), (3, 4, 20)]);
+        assert!(graph.incoming(0).is_empty() && graph.outgoing(4).is_empty());
+    }
+
+    #[test]
+    fn dot() {
+        let dot = graph(false).to_dot();
+        assert!(dot.starts_with("digraph lattice {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert!(dot.contains("  n0 [label=\"BOS\\n[0, 0) wcost=0\", color=red, penwidth=2];\n"));
+        assert!(dot.contains("  n1 [label=\"a\\\"b\\n名詞,固有名詞,*,*\\n[0, 3) wcost=100\"];\n"));
+        assert!(dot.contains("  n3 [label=\"\\\"b\\n記号,\\\"括弧\\\\開\\\",*,*\\n[1, 3) wcost=10\", color=red, penwidth=2];\n"));
+        assert!(dot.contains("  n2 -> n3 [label=\"12\", color=red, penwidth=2];\n"));
+        assert!(dot.contains("  n1 -> n4 [label=\"30\"];\n"));
+    }
+
+    #[test]
+    fn marginal_dot() {
+        let dot = graph(true).to_dot();
+        assert!(dot.contains("[0, 1) wcost=10 prob=0.7500\""));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_round_trip() {
+        let graph = graph(true);
+        assert_eq!(graph.z, Some(-1.5));
+        assert_eq!(graph.nodes[2].prob, Some(0.75));
+
+        let loaded = LatticeGraph::from_json(&graph.to_json()).unwrap();
+        assert_eq!(loaded.nodes, graph.nodes);
+        assert_eq!(loaded.edges, graph.edges);
+        assert_eq!(loaded.best_path, graph.best_path);
+        assert_eq!(loaded.z, Some(-1.5));
+        assert_eq!(loaded, graph);
+
+        assert!(LatticeGraph::from_json("{\"sentence\": \"a\"}").is_err());
+    }
+}
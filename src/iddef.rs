@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use format::FormatError;
+use mecab::{Model, Node, MECAB_SYS_DIC};
+use token::Token;
+
+const POS_ID_DEF: &str = "pos-id.def";
+const LEFT_ID_DEF: &str = "left-id.def";
+const RIGHT_ID_DEF: &str = "right-id.def";
+
+#[derive(Debug)]
+pub enum DefinitionError {
+    /// The model has no system dictionary to locate the definitions with.
+    NoDicdir,
+    Io(PathBuf, io::Error),
+    /// A definition file is not UTF-8 or has a malformed line.
+    Format(String, FormatError),
+}
+
+impl fmt::Display for DefinitionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DefinitionError::NoDicdir => write!(f, "the model has no system dictionary"),
+            DefinitionError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            DefinitionError::Format(file, e) => write!(f, "{}: {}", file, e),
+        }
+    }
+}
+
+impl Error for DefinitionError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DefinitionError::Io(_, e) => Some(e),
+            DefinitionError::Format(_, e) => Some(e),
+            DefinitionError::NoDicdir => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct IdTable {
+    names: HashMap<u16, String>,
+    ids: HashMap<String, u16>,
+}
+
+impl IdTable {
+    // `pos-id.def` puts the name first, the context id files put the id first
+    fn parse(text: &str, id_first: bool) -> Result<IdTable, FormatError> {
+        let mut table = IdTable::default();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let fields = if id_first {
+                line.split_once(char::is_whitespace)
+            } else {
+                line.rsplit_once(char::is_whitespace).map(|(name, id)| (id, name))
+            };
+            let (id, name) = fields.ok_or_else(|| FormatError::new(i + 1, "expected an id and a name"))?;
+            let id: u16 = id.parse().map_err(|_| FormatError::new(i + 1, format!("invalid id `{}`", id)))?;
+            let name = name.trim();
+
+            table.ids.entry(name.to_owned()).or_insert(id);
+            table.names.insert(id, name.to_owned());
+        }
+        Ok(table)
+    }
+
+    fn name(&self, id: u16) -> Option<&str> {
+        self.names.get(&id).map(|s| s.as_str())
+    }
+
+    fn id(&self, name: &str) -> Option<u16> {
+        self.ids.get(name).cloned()
+    }
+}
+
+/// Names of the POS and context ids of a dictionary from its `pos-id.def`,
+/// `left-id.def` and `right-id.def`.
+#[derive(Clone, Debug, Default)]
+pub struct IdDefinitions {
+    pos: IdTable,
+    left: IdTable,
+    right: IdTable,
+}
+
+impl IdDefinitions {
+    /// Reads the definitions of the dictionary in `dicdir`, missing files leave their ids unnamed.
+    pub fn load<P: AsRef<Path>>(dicdir: P) -> Result<IdDefinitions, DefinitionError> {
+        let dicdir = dicdir.as_ref();
+        let read = |file: &str| {
+            let path = dicdir.join(file);
+            match fs::read(&path) {
+                Ok(bytes) => {
+                    String::from_utf8(bytes)
+                        .map_err(|_| DefinitionError::Format(file.to_owned(), FormatError::new(1, "not UTF-8")))
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+                Err(e) => Err(DefinitionError::Io(path, e)),
+            }
+        };
+
+        IdDefinitions::parse(&read(POS_ID_DEF)?, &read(LEFT_ID_DEF)?, &read(RIGHT_ID_DEF)?)
+    }
+
+    /// Parses the contents of `pos-id.def`, `left-id.def` and `right-id.def`.
+    pub fn parse(pos_id: &str, left_id: &str, right_id: &str) -> Result<IdDefinitions, DefinitionError> {
+        let table = |text, id_first, file: &str| {
+            IdTable::parse(text, id_first).map_err(|e| DefinitionError::Format(file.to_owned(), e))
+        };
+
+        Ok(IdDefinitions {
+            pos: table(pos_id, false, POS_ID_DEF)?,
+            left: table(left_id, true, LEFT_ID_DEF)?,
+            right: table(right_id, true, RIGHT_ID_DEF)?,
+        })
+    }
+
+    /// POS pattern of a POS id such as `名詞,一般,*,*`.
+    pub fn pos_name(&self, posid: u16) -> Option<&str> {
+        self.pos.name(posid)
+    }
+
+    pub fn pos_id(&self, name: &str) -> Option<u16> {
+        self.pos.id(name)
+    }
+
+    /// Ids of every POS whose name starts with `pattern`, `*` matches any field.
+    pub fn pos_ids_matching<S: AsRef<str>>(&self, pattern: &[S]) -> Vec<u16> {
+        let mut ids: Vec<u16> = self.pos
+            .names
+            .iter()
+            .filter(|&(_, name)| {
+                let fields: Vec<&str> = name.split(',').collect();
+                pattern.len() <= fields.len() &&
+                pattern.iter().zip(&fields).all(|(p, f)| p.as_ref() == "*" || p.as_ref() == *f)
+            })
+            .map(|(&id, _)| id)
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    /// Feature pattern of a left context id, as in `lcattr`.
+    pub fn left_context_name(&self, id: u16) -> Option<&str> {
+        self.left.name(id)
+    }
+
+    pub fn left_context_id(&self, name: &str) -> Option<u16> {
+        self.left.id(name)
+    }
+
+    /// Feature pattern of a right context id, as in `rcattr`.
+    pub fn right_context_name(&self, id: u16) -> Option<&str> {
+        self.right.name(id)
+    }
+
+    pub fn right_context_id(&self, name: &str) -> Option<u16> {
+        self.right.id(name)
+    }
+}
+
+impl Model {
+    /// Directory of the system dictionary.
+    pub fn dicdir(&self) -> Option<PathBuf> {
+        self.dictionary_info()
+            .iter()
+            .find(|dict| dict.dict_type == MECAB_SYS_DIC)
+            .and_then(|dict| Path::new(&dict.filename).parent().map(Path::to_path_buf))
+    }
+
+    /// Reads the id definitions from the dicdir of the system dictionary.
+    pub fn id_definitions(&self) -> Result<IdDefinitions, DefinitionError> {
+        IdDefinitions::load(self.dicdir().ok_or(DefinitionError::NoDicdir)?)
+    }
+}
+
+impl Node {
+    pub fn pos_name<'a>(&self, defs: &'a IdDefinitions) -> Option<&'a str> {
+        defs.pos_name(self.posid)
+    }
+
+    pub fn left_context_name<'a>(&self, defs: &'a IdDefinitions) -> Option<&'a str> {
+        defs.left_context_name(self.lcattr)
+    }
+
+    pub fn right_context_name<'a>(&self, defs: &'a IdDefinitions) -> Option<&'a str> {
+        defs.right_context_name(self.rcattr)
+    }
+}
+
+impl Token {
+    pub fn pos_name<'a>(&self, defs: &'a IdDefinitions) -> Option<&'a str> {
+        defs.pos_name(self.posid)
+    }
+
+    pub fn left_context_name<'a>(&self, defs: &'a IdDefinitions) -> Option<&'a str> {
+        defs.left_context_name(self.lcattr)
+    }
+
+    pub fn right_context_name<'a>(&self, defs: &'a IdDefinitions) -> Option<&'a str> {
+        defs.right_context_name(self.rcattr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::process;
+
+    use super::*;
+
+    const POS_ID: &str = "その他,間投,*,* 0\n\
+                          名詞,一般,*,* 38\n\
+                          名詞,固有名詞,地域,一般 46\n\
+                          助詞,係助詞,*,* 16\n";
+    const LEFT_ID: &str = "0 BOS/EOS,*,*,*,*,*,BOS/EOS\n\
+                           1285 名詞,一般,*,*,*,*,*\n";
+    const RIGHT_ID: &str = "0 BOS/EOS,*,*,*,*,*,BOS/EOS\n\
+                            \n\
+                            1285 名詞,一般,*,*,*,*,*\n";
+
+    #[test]
+    fn names_and_ids() {
+        let defs = IdDefinitions::parse(POS_ID, LEFT_ID, RIGHT_ID).unwrap();
+        assert_eq!(defs.pos_name(38), Some("名詞,一般,*,*"));
+        assert_eq!(defs.pos_id("助詞,係助詞,*,*"), Some(16));
+        assert_eq!(defs.pos_name(1), None);
+        assert_eq!(defs.left_context_name(1285), Some("名詞,一般,*,*,*,*,*"));
+        assert_eq!(defs.left_context_id("BOS/EOS,*,*,*,*,*,BOS/EOS"), Some(0));
+        assert_eq!(defs.right_context_id("名詞,一般,*,*,*,*,*"), Some(1285));
+    }
+
+    #[test]
+    fn pos_ids_matching() {
+        let defs = IdDefinitions::parse(POS_ID, "", "").unwrap();
+        assert_eq!(defs.pos_ids_matching(&["名詞"]), [38, 46]);
+        assert_eq!(defs.pos_ids_matching(&["*", "固有名詞"]), [46]);
+        assert_eq!(defs.pos_ids_matching(&["名詞", "*", "*", "*", "*"]), Vec::<u16>::new());
+        assert_eq!(defs.pos_ids_matching::<&str>(&[]).len(), 4);
+    }
+
+    #[test]
+    fn malformed_lines() {
+        match IdDefinitions::parse(POS_ID, "0 BOS/EOS\nx 名詞\n", "") {
+            Err(DefinitionError::Format(file, e)) => {
+                assert_eq!(file, LEFT_ID_DEF);
+                assert_eq!(e.line, 2);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(IdDefinitions::parse("名詞,一般,*,*\n", "", "").is_err());
+    }
+
+    #[test]
+    fn load_without_files() {
+        let dicdir = env::temp_dir().join(format!("mecab-iddef-{}", process::id()));
+        fs::create_dir_all(&dicdir).unwrap();
+        fs::write(dicdir.join(POS_ID_DEF), POS_ID).unwrap();
+        let defs = IdDefinitions::load(&dicdir);
+        fs::remove_dir_all(&dicdir).unwrap();
+
+        let defs = defs.unwrap();
+        assert_eq!(defs.pos_id("名詞,一般,*,*"), Some(38));
+        assert_eq!(defs.left_context_name(0), None);
+    }
+}
@@ -0,0 +1,73 @@
+use std::char;
+
+pub fn is_hiragana(c: char) -> bool {
+    matches!(c, '\u{3041}'..='\u{309F}')
+}
+
+/// Katakana including the prolonged sound mark and half-width katakana.
+pub fn is_katakana(c: char) -> bool {
+    matches!(c, '\u{30A0}'..='\u{30FF}' | '\u{31F0}'..='\u{31FF}' | '\u{FF66}'..='\u{FF9D}')
+}
+
+pub fn is_kana(c: char) -> bool {
+    is_hiragana(c) || is_katakana(c)
+}
+
+/// CJK ideographs and the iteration marks that stand in for them.
+pub fn is_kanji(c: char) -> bool {
+    matches!(c,
+             '\u{4E00}'..='\u{9FFF}' | '\u{3400}'..='\u{4DBF}' | '\u{F900}'..='\u{FAFF}' |
+             '\u{20000}'..='\u{2FFFF}' | '々' | '〆' | 'ヶ' | '〇')
+}
+
+pub fn katakana_to_hiragana(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\u{30A1}'..='\u{30F6}' | '\u{30FD}'..='\u{30FE}' => {
+                char::from_u32(c as u32 - 0x60).unwrap()
+            }
+            _ => c,
+        })
+        .collect()
+}
+
+pub fn hiragana_to_katakana(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\u{3041}'..='\u{3096}' | '\u{309D}'..='\u{309E}' => {
+                char::from_u32(c as u32 + 0x60).unwrap()
+            }
+            _ => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversion_round_trip() {
+        let hiragana = "ぁあゔゕゖゝゞがっこう";
+        let katakana = "ァアヴヵヶヽヾガッコウ";
+        assert_eq!(hiragana_to_katakana(hiragana), katakana);
+        assert_eq!(katakana_to_hiragana(katakana), hiragana);
+        // the prolonged sound mark, ヷ and other text stay as they are
+        assert_eq!(katakana_to_hiragana("ラーメンヷ東京a"), "らーめんヷ東京a");
+        assert_eq!(hiragana_to_katakana("ゟ。"), "ゟ。");
+    }
+
+    #[test]
+    fn ranges() {
+        assert!(is_hiragana('ぁ') && is_hiragana('ゟ') && !is_hiragana('ァ'));
+        assert!(is_katakana('ー') && is_katakana('ㇰ') && is_katakana('ｶ') && !is_katakana('ﾞ'));
+        assert!(is_kana('か') && is_kana('カ') && !is_kana('字') && !is_kana('a'));
+
+        for &c in &['漢', '㐀', '丽', '𠀋', '々', '〆', 'ヶ', '〇'] {
+            assert!(is_kanji(c), "{}", c);
+        }
+        for &c in &['か', 'カ', '。', 'Ａ', '\u{4DC0}'] {
+            assert!(!is_kanji(c), "{}", c);
+        }
+    }
+}
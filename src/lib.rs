@@ -1,5 +1,69 @@
 #![crate_name = "mecab"]
 #![crate_type = "lib"]
 
+extern crate unicode_normalization;
+#[cfg(feature = "json")]
+extern crate serde;
+#[cfg(feature = "json")]
+extern crate serde_json;
+#[cfg(feature = "tantivy")]
+extern crate tantivy_tokenizer_api;
+#[cfg(feature = "python")]
+extern crate pyo3;
+// the pyo3 macros refer to `::core`, which the 2015 edition does not put in scope
+#[cfg(feature = "python")]
+extern crate core;
+
 pub use mecab::*;
+pub use constraints::*;
+pub use token::*;
+pub use offset::*;
+pub use nbest::*;
+pub use marginal::*;
+pub use graph::*;
+pub use format::*;
+pub use normalize::*;
+pub use kana::*;
+pub use furigana::*;
+pub use romaji::*;
+pub use conjugation::*;
+pub use bunsetsu::*;
+pub use sentence::*;
+pub use pool::*;
+pub use filter::*;
+pub use decompound::*;
+pub use iddef::*;
+pub use matrix::*;
+pub use explain::*;
+pub use score::*;
+pub use tools::*;
+#[cfg(feature = "tantivy")]
+pub use tantivy::*;
+
 mod mecab;
+mod constraints;
+mod token;
+mod offset;
+mod nbest;
+mod marginal;
+mod graph;
+mod format;
+mod normalize;
+mod kana;
+mod furigana;
+mod romaji;
+mod conjugation;
+mod bunsetsu;
+mod sentence;
+mod pool;
+mod filter;
+mod decompound;
+mod iddef;
+mod matrix;
+mod explain;
+mod score;
+mod tools;
+#[cfg(feature = "tantivy")]
+mod tantivy;
+#[cfg(feature = "python")]
+mod python;
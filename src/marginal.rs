@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+
+use mecab::{Lattice, Tagger, MECAB_MARGINAL_PROB, MECAB_ONE_BEST};
+use offset::OffsetMap;
+use token::{is_boundary_node, Token};
+
+/// Marginal probabilities of a sentence parsed with `MECAB_MARGINAL_PROB`.
+///
+/// Per-position vectors are indexed by char position; `boundaries` has one
+/// more entry than there are chars so that both sentence edges are included.
+#[derive(Clone, Debug)]
+pub struct Marginals {
+    pub sentence: String,
+    pub theta: f64,
+    pub z: f64,
+    /// Every candidate morpheme of the lattice, `Token::prob` holds its marginal probability.
+    pub morphemes: Vec<Token>,
+    /// Probability that a word boundary exists before each char.
+    pub boundaries: Vec<f64>,
+    /// Expected distribution of the top-level POS covering each char.
+    pub pos: Vec<HashMap<String, f64>>,
+}
+
+impl Marginals {
+    pub fn from_lattice(lattice: &Lattice) -> Marginals {
+        let sentence = lattice.sentence();
+        let len = lattice.size();
+
+        let offsets = OffsetMap::new(&sentence);
+        let chars = offsets.char_len();
+
+        let mut morphemes = Vec::new();
+        for pos in 0..len + 1 {
+            if let Some(nodes) = lattice.begin_nodes(pos) {
+                morphemes.extend(nodes.iter_bnext()
+                    .filter(|node| !is_boundary_node(node))
+                    .map(|node| Token::from_node(&node, &offsets)));
+            }
+        }
+
+        let mut boundaries = vec![0.0; chars + 1];
+        for (i, boundary) in boundaries.iter_mut().enumerate() {
+            if i == 0 || i == chars {
+                *boundary = 1.0;
+            } else if let Some(nodes) = lattice.end_nodes(offsets.char_to_byte(i).unwrap()) {
+                *boundary = nodes.iter_enext()
+                    .filter(|node| !is_boundary_node(node))
+                    .map(|node| node.prob as f64)
+                    .sum();
+            }
+        }
+
+        let mut pos = vec![HashMap::new(); chars];
+        for token in &morphemes {
+            let tag = token.features()[0].to_owned();
+            for dist in &mut pos[token.char_span.begin..token.char_span.end] {
+                *dist.entry(tag.clone()).or_insert(0.0) += token.prob as f64;
+            }
+        }
+
+        Marginals {
+            theta: lattice.theta(),
+            z: lattice.z(),
+            sentence,
+            morphemes,
+            boundaries,
+            pos,
+        }
+    }
+
+    /// Most likely top-level POS at a char position.
+    ///
+    /// Probabilities that are not finite, as when theta or Z overflowed, are skipped.
+    pub fn best_pos(&self, position: usize) -> Option<(&str, f64)> {
+        self.pos
+            .get(position)
+            .and_then(|dist| {
+                dist.iter()
+                    .filter(|&(_, p)| p.is_finite())
+                    .max_by(|a, b| a.1.total_cmp(b.1))
+                    .map(|(tag, &p)| (tag.as_str(), p))
+            })
+    }
+}
+
+impl Tagger {
+    /// Parses `input` with marginal probabilities enabled, returns `None` if parsing fails.
+    pub fn marginals<T: Into<Vec<u8>>>(&self, input: T) -> Option<Marginals> {
+        let mut lattice = Lattice::new();
+        lattice.set_request_type(MECAB_ONE_BEST | MECAB_MARGINAL_PROB);
+        lattice.set_sentence(input);
+
+        if self.parse(&lattice) {
+            Some(Marginals::from_lattice(&lattice))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mecab::MockNode;
+
+    // すもも as one noun or as a prefix and a noun
+    fn lattice(noun_prob: f32) -> Lattice {
+        let mut lattice = Lattice::new();
+        lattice.set_request_type(MECAB_ONE_BEST | MECAB_MARGINAL_PROB);
+        lattice.set_sentence("すもも");
+        lattice.mock_nodes(&[MockNode::new(0, 9, "名詞,一般,*,*,*,*,すもも,スモモ,スモモ", 100).prob(noun_prob),
+                             MockNode::new(0, 3, "接頭詞,名詞接続,*,*,*,*,す,ス,ス", 80).prob(0.25),
+                             MockNode::new(3, 6, "名詞,一般,*,*,*,*,もも,モモ,モモ", 80).prob(0.25)],
+                           |_, _| 0);
+        lattice.set_z(-12.5);
+        lattice
+    }
+
+    #[test]
+    fn boundaries() {
+        let marginals = Marginals::from_lattice(&lattice(0.75));
+        assert_eq!(marginals.sentence, "すもも");
+        assert_eq!(marginals.z, -12.5);
+        assert_eq!(marginals.morphemes.len(), 3);
+        assert_eq!(marginals.boundaries, [1.0, 0.25, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn pos_distribution() {
+        let marginals = Marginals::from_lattice(&lattice(0.75));
+        assert_eq!(marginals.pos.len(), 3);
+        assert_eq!(marginals.pos[0]["名詞"], 0.75);
+        assert_eq!(marginals.pos[0]["接頭詞"], 0.25);
+        assert_eq!(marginals.pos[2]["名詞"], 1.0);
+        assert!(!marginals.pos[2].contains_key("接頭詞"));
+
+        assert_eq!(marginals.best_pos(0), Some(("名詞", 0.75)));
+        assert_eq!(marginals.best_pos(3), None);
+    }
+
+    #[test]
+    fn best_pos_skips_nan() {
+        let marginals = Marginals::from_lattice(&lattice(f32::NAN));
+        assert!(marginals.pos[0]["名詞"].is_nan());
+        assert_eq!(marginals.best_pos(0), Some(("接頭詞", 0.25)));
+        assert_eq!(marginals.best_pos(1), None);
+    }
+}
@@ -0,0 +1,236 @@
+use std::cmp::Reverse;
+use std::io::{self, Write};
+
+use mecab::{Model, MECAB_SYS_DIC};
+
+/// Connection costs of a dictionary, indexed by the right context id of the
+/// left node and the left context id of the right node.
+///
+/// Right context ids run from 0 to `lsize` and left context ids from 0 to
+/// `rsize`, the sizes reported by `DictionaryInfo`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConnectionMatrix {
+    lsize: usize,
+    rsize: usize,
+    // one row of successor costs per right context id
+    costs: Vec<i16>,
+}
+
+/// Connection whose cost differs between two matrices.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CostDifference {
+    pub rc_attr: u16,
+    pub lc_attr: u16,
+    pub old: i16,
+    pub new: i16,
+}
+
+impl ConnectionMatrix {
+    /// Copies the matrix of the system dictionary of `model`.
+    pub fn new(model: &Model) -> ConnectionMatrix {
+        let info = model.dictionary_info();
+        let sys = info.clone().iter().find(|dict| dict.dict_type == MECAB_SYS_DIC).unwrap_or(info);
+        let (lsize, rsize) = (sys.lsize as usize, sys.rsize as usize);
+
+        let mut costs = Vec::with_capacity(lsize * rsize);
+        for rc in 0..lsize {
+            for lc in 0..rsize {
+                costs.push(model.transition_cost(rc as u16, lc as u16) as i16);
+            }
+        }
+
+        ConnectionMatrix { lsize, rsize, costs }
+    }
+
+    /// Builds a matrix from row-major costs, `None` if there are not `lsize * rsize` of them.
+    pub fn from_costs(lsize: usize, rsize: usize, costs: Vec<i16>) -> Option<ConnectionMatrix> {
+        if costs.len() == lsize * rsize {
+            Some(ConnectionMatrix { lsize, rsize, costs })
+        } else {
+            None
+        }
+    }
+
+    /// Number of right context ids.
+    pub fn lsize(&self) -> usize {
+        self.lsize
+    }
+
+    /// Number of left context ids.
+    pub fn rsize(&self) -> usize {
+        self.rsize
+    }
+
+    pub fn get(&self, rc_attr: u16, lc_attr: u16) -> Option<i16> {
+        let (rc, lc) = (rc_attr as usize, lc_attr as usize);
+        if rc < self.lsize && lc < self.rsize {
+            Some(self.costs[rc * self.rsize + lc])
+        } else {
+            None
+        }
+    }
+
+    /// Costs of every successor of `rc_attr`, indexed by left context id.
+    pub fn row(&self, rc_attr: u16) -> &[i16] {
+        let begin = rc_attr as usize * self.rsize;
+        &self.costs[begin..begin + self.rsize]
+    }
+
+    /// Costs of every predecessor of `lc_attr`, indexed by right context id.
+    pub fn column(&self, lc_attr: u16) -> Vec<i16> {
+        self.costs.iter().skip(lc_attr as usize).step_by(self.rsize).cloned().collect()
+    }
+
+    /// All costs, one row of `rsize` successors per right context id.
+    pub fn as_slice(&self) -> &[i16] {
+        &self.costs
+    }
+
+    /// The `n` left context ids that connect to `rc_attr` most cheaply, with their costs.
+    pub fn cheapest_successors(&self, rc_attr: u16, n: usize) -> Vec<(u16, i16)> {
+        let mut successors = self.successors(rc_attr);
+        successors.sort_by_key(|&(lc, cost)| (cost, lc));
+        successors.truncate(n);
+        successors
+    }
+
+    /// The `n` left context ids that connect to `rc_attr` most expensively, with their costs.
+    pub fn most_expensive_successors(&self, rc_attr: u16, n: usize) -> Vec<(u16, i16)> {
+        let mut successors = self.successors(rc_attr);
+        successors.sort_by_key(|&(lc, cost)| (Reverse(cost), lc));
+        successors.truncate(n);
+        successors
+    }
+
+    fn successors(&self, rc_attr: u16) -> Vec<(u16, i16)> {
+        self.row(rc_attr).iter().enumerate().map(|(lc, &cost)| (lc as u16, cost)).collect()
+    }
+
+    /// Connections whose cost differs in `other`, `None` if the matrices have different sizes.
+    pub fn diff(&self, other: &ConnectionMatrix) -> Option<Vec<CostDifference>> {
+        if self.lsize != other.lsize || self.rsize != other.rsize {
+            return None;
+        }
+
+        Some(self.costs
+            .iter()
+            .zip(&other.costs)
+            .enumerate()
+            .filter(|&(_, (old, new))| old != new)
+            .map(|(i, (&old, &new))| {
+                CostDifference {
+                    rc_attr: (i / self.rsize) as u16,
+                    lc_attr: (i % self.rsize) as u16,
+                    old,
+                    new,
+                }
+            })
+            .collect())
+    }
+
+    /// Writes one line of comma separated successor costs per right context id.
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        for rc in 0..self.lsize {
+            let row: Vec<String> = self.row(rc as u16).iter().map(|c| c.to_string()).collect();
+            writeln!(writer, "{}", row.join(","))?;
+        }
+        Ok(())
+    }
+
+    /// Writes the matrix as a NumPy `.npy` array of shape `(lsize, rsize)` and type `int16`.
+    pub fn write_npy<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let mut header = format!("{{'descr': '<i2', 'fortran_order': False, 'shape': ({}, {}), }}",
+                                 self.lsize,
+                                 self.rsize);
+        // magic, version and header length take 10 bytes, the data starts 64-byte aligned
+        while (10 + header.len() + 1) % 64 != 0 {
+            header.push(' ');
+        }
+        header.push('\n');
+
+        writer.write_all(b"\x93NUMPY\x01\x00")?;
+        writer.write_all(&(header.len() as u16).to_le_bytes())?;
+        writer.write_all(header.as_bytes())?;
+        let mut data = Vec::with_capacity(self.costs.len() * 2);
+        for cost in &self.costs {
+            data.extend_from_slice(&cost.to_le_bytes());
+        }
+        writer.write_all(&data)
+    }
+}
+
+impl Model {
+    /// Copies the whole connection matrix, see `ConnectionMatrix::new`.
+    pub fn connection_matrix(&self) -> ConnectionMatrix {
+        ConnectionMatrix::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2 right context ids and 3 left context ids
+    fn matrix() -> ConnectionMatrix {
+        ConnectionMatrix::from_costs(2, 3, vec![0, -5, 10, 7, 7, -300]).unwrap()
+    }
+
+    #[test]
+    fn lookup() {
+        let matrix = matrix();
+        assert_eq!(matrix.get(1, 2), Some(-300));
+        assert_eq!(matrix.get(2, 0), None);
+        assert_eq!(matrix.get(0, 3), None);
+        assert_eq!(matrix.row(1), [7, 7, -300]);
+        assert_eq!(matrix.column(1), [-5, 7]);
+        assert!(ConnectionMatrix::from_costs(2, 3, vec![0; 5]).is_none());
+    }
+
+    #[test]
+    fn successors() {
+        let matrix = matrix();
+        assert_eq!(matrix.cheapest_successors(0, 2), [(1, -5), (0, 0)]);
+        // ties are ordered by left context id
+        assert_eq!(matrix.most_expensive_successors(1, 2), [(0, 7), (1, 7)]);
+        assert_eq!(matrix.cheapest_successors(1, 10).len(), 3);
+    }
+
+    #[test]
+    fn diff() {
+        let matrix = matrix();
+        let other = ConnectionMatrix::from_costs(2, 3, vec![0, -5, 10, 7, 8, -300]).unwrap();
+        assert_eq!(matrix.diff(&other),
+                   Some(vec![CostDifference {
+                                 rc_attr: 1,
+                                 lc_attr: 1,
+                                 old: 7,
+                                 new: 8,
+                             }]));
+        assert_eq!(matrix.diff(&matrix), Some(Vec::new()));
+        assert_eq!(matrix.diff(&ConnectionMatrix::from_costs(3, 2, vec![0; 6]).unwrap()), None);
+    }
+
+    #[test]
+    fn csv() {
+        let mut out = Vec::new();
+        matrix().write_csv(&mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "0,-5,10\n7,7,-300\n");
+    }
+
+    #[test]
+    fn npy() {
+        let mut out = Vec::new();
+        matrix().write_npy(&mut out).unwrap();
+
+        assert_eq!(&out[..8], b"\x93NUMPY\x01\x00");
+        let header_len = u16::from_le_bytes([out[8], out[9]]) as usize;
+        let data = 10 + header_len;
+        assert_eq!(data % 64, 0);
+        let header = ::std::str::from_utf8(&out[10..data]).unwrap();
+        assert!(header.starts_with("{'descr': '<i2', 'fortran_order': False, 'shape': (2, 3), }"));
+        assert!(header.ends_with('\n'));
+
+        let costs: Vec<i16> = out[data..].chunks(2).map(|b| i16::from_le_bytes([b[0], b[1]])).collect();
+        assert_eq!(costs, matrix().as_slice());
+    }
+}
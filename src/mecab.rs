@@ -32,19 +32,14 @@ pub const MECAB_INSIDE_TOKEN: i32 = 2;
 
 #[link(name="mecab")]
 extern "C" {
-    fn mecab_new2(arg: *const c_char) -> *mut c_void;
     fn mecab_version() -> *const c_char;
     fn mecab_strerror(mecab: *mut c_void) -> *const c_char;
-    fn mecab_destroy(mecab: *mut c_void);
     fn mecab_get_partial(mecab: *mut c_void) -> c_int;
     fn mecab_set_partial(mecab: *mut c_void, partial: c_int);
-    fn mecab_get_theta(mecab: *mut c_void) -> c_float;
-    fn mecab_set_theta(mecab: *mut c_void, theta: c_float);
     fn mecab_get_lattice_level(mecab: *mut c_void) -> c_int;
     fn mecab_set_lattice_level(mecab: *mut c_void, level: c_int);
     fn mecab_get_all_morphs(mecab: *mut c_void) -> c_int;
     fn mecab_set_all_morphs(mecab: *mut c_void, all_morphs: c_int);
-    fn mecab_parse_lattice(mecab: *mut c_void, lattice: *mut c_void) -> c_int;
     fn mecab_sparse_tostr(mecab: *mut c_void, str: *const c_char) -> *const c_char;
     fn mecab_sparse_tonode(mecab: *mut c_void, str: *const c_char) -> *const raw_node;
     fn mecab_nbest_sparse_tostr(mecab: *mut c_void,
@@ -57,6 +52,36 @@ extern "C" {
     fn mecab_format_node(mecab: *mut c_void, node: *const raw_node) -> *const c_char;
     fn mecab_dictionary_info(mecab: *mut c_void) -> *const dictionary_info_t;
 
+    fn mecab_model_swap(model: *mut c_void, new_model: *mut c_void) -> c_int;
+    fn mecab_model_dictionary_info(model: *mut c_void) -> *const dictionary_info_t;
+    fn mecab_model_transition_cost(model: *mut c_void,
+                                   rcAttr: c_ushort,
+                                   lcAttr: c_ushort)
+                                   -> c_int;
+    fn mecab_model_lookup(model: *mut c_void,
+                          begin: *const c_char,
+                          end: *const c_char,
+                          lattice: *mut c_void)
+                          -> *const raw_node;
+}
+
+// the lattice functions and the tagger and model functions needed to parse
+// are replaced by the mocks in `mock` when testing
+#[cfg(not(test))]
+#[link(name="mecab")]
+extern "C" {
+    fn mecab_new(argc: c_int, argv: *mut *mut c_char) -> *mut c_void;
+    fn mecab_new2(arg: *const c_char) -> *mut c_void;
+    fn mecab_destroy(mecab: *mut c_void);
+    fn mecab_get_theta(mecab: *mut c_void) -> c_float;
+    fn mecab_set_theta(mecab: *mut c_void, theta: c_float);
+    fn mecab_parse_lattice(mecab: *mut c_void, lattice: *mut c_void) -> c_int;
+    fn mecab_model_new(argc: c_int, argv: *mut *mut c_char) -> *mut c_void;
+    fn mecab_model_new2(arg: *const c_char) -> *mut c_void;
+    fn mecab_model_destroy(model: *mut c_void);
+    fn mecab_model_new_tagger(model: *mut c_void) -> *mut c_void;
+    fn mecab_model_new_lattice(model: *mut c_void) -> *mut c_void;
+
     fn mecab_lattice_new() -> *mut c_void;
     fn mecab_lattice_destroy(lattice: *mut c_void);
     fn mecab_lattice_clear(lattice: *mut c_void);
@@ -66,7 +91,7 @@ extern "C" {
     fn mecab_lattice_get_begin_nodes(lattice: *mut c_void, pos: size_t) -> *const raw_node;
     fn mecab_lattice_get_end_nodes(lattice: *mut c_void, pos: size_t) -> *const raw_node;
     fn mecab_lattice_get_sentence(lattice: *mut c_void) -> *const c_char;
-    fn mecab_lattice_set_sentence(lattice: *mut c_void, sentence: *const c_char);
+    fn mecab_lattice_set_sentence2(lattice: *mut c_void, sentence: *const c_char, len: size_t);
     fn mecab_lattice_get_size(lattice: *mut c_void) -> size_t;
     fn mecab_lattice_get_z(lattice: *mut c_void) -> c_double;
     fn mecab_lattice_set_z(lattice: *mut c_void, Z: c_double);
@@ -90,24 +115,15 @@ extern "C" {
                                             feature: *const c_char);
     fn mecab_lattice_set_result(lattice: *mut c_void, result: *const c_char);
     fn mecab_lattice_strerror(lattice: *mut c_void) -> *const c_char;
-
-    fn mecab_model_new2(arg: *const c_char) -> *mut c_void;
-    fn mecab_model_destroy(model: *mut c_void);
-    fn mecab_model_new_tagger(model: *mut c_void) -> *mut c_void;
-    fn mecab_model_new_lattice(model: *mut c_void) -> *mut c_void;
-    fn mecab_model_swap(model: *mut c_void, new_model: *mut c_void) -> c_int;
-    fn mecab_model_dictionary_info(model: *mut c_void) -> *const dictionary_info_t;
-    fn mecab_model_transition_cost(model: *mut c_void,
-                                   rcAttr: c_ushort,
-                                   lcAttr: c_ushort)
-                                   -> c_int;
-    fn mecab_model_lookup(model: *mut c_void,
-                          begin: *const c_char,
-                          end: *const c_char,
-                          lattice: *mut c_void)
-                          -> *const raw_node;
 }
 
+#[cfg(test)]
+use self::mock::*;
+#[cfg(test)]
+pub(crate) use self::mock::MockNode;
+#[cfg(test)]
+mod mock;
+
 pub fn version() -> String {
     unsafe { ptr_to_string(mecab_version()) }
 }
@@ -117,6 +133,9 @@ pub struct Tagger {
     input: *const c_char,
 }
 
+// a tagger may move to another thread but is not safe to share
+unsafe impl Send for Tagger {}
+
 impl Tagger {
     pub fn new<T: Into<Vec<u8>>>(arg: T) -> Tagger {
         unsafe {
@@ -127,6 +146,21 @@ impl Tagger {
         }
     }
 
+    // takes the arguments as separate strings so that values such as `-F` formats may contain spaces
+    pub fn with_args<T: AsRef<str>>(args: &[T]) -> Tagger {
+        unsafe {
+            Tagger {
+                inner: new_with_args(args, mecab_new),
+                input: ptr::null(),
+            }
+        }
+    }
+
+    // false if libmecab failed to create the tagger, see `get_last_error`
+    pub fn is_available(&self) -> bool {
+        !self.inner.is_null()
+    }
+
     fn free_input(&mut self) {
         unsafe {
             if !self.input.is_null() {
@@ -255,32 +289,31 @@ impl Drop for Tagger {
 
 pub struct Lattice {
     inner: *mut c_void,
-    input: *const c_char,
+    // libmecab only borrows the sentence, it has to outlive every parse
+    input: Vec<u8>,
+    // libmecab keeps the raw pointers passed as feature constraints
+    features: Vec<Vec<u8>>,
 }
 
+unsafe impl Send for Lattice {}
+
 impl Lattice {
     pub fn new() -> Lattice {
         unsafe {
             Lattice {
                 inner: mecab_lattice_new(),
-                input: ptr::null(),
+                input: Vec::new(),
+                features: Vec::new(),
             }
         }
     }
 
-    fn free_input(&self) {
-        unsafe {
-            if !self.input.is_null() {
-                let _ = CString::from_raw(self.input as *mut c_char);
-            }
-        }
-    }
-
-    pub fn clear(&self) {
+    pub fn clear(&mut self) {
         unsafe {
             mecab_lattice_clear(self.inner);
-            self.free_input();
         }
+        self.input = Vec::new();
+        self.features.clear();
     }
 
     pub fn is_available(&self) -> bool {
@@ -318,15 +351,26 @@ impl Lattice {
     }
 
     pub fn sentence(&self) -> String {
-        unsafe { ptr_to_string(mecab_lattice_get_sentence(self.inner)) }
+        unsafe {
+            let ptr = mecab_lattice_get_sentence(self.inner);
+            if !ptr.is_null() {
+                ptr_to_string(ptr)
+            } else {
+                String::new()
+            }
+        }
     }
 
     pub fn set_sentence<T: Into<Vec<u8>>>(&mut self, sentence: T) {
+        let input = CString::new(sentence).unwrap().into_bytes_with_nul();
         unsafe {
-            self.free_input();
-            self.input = str_to_heap_ptr(sentence);
-            mecab_lattice_set_sentence(self.inner, self.input);
+            mecab_lattice_set_sentence2(self.inner,
+                                        input.as_ptr() as *const c_char,
+                                        input.len() - 1);
         }
+        // the previous sentence is only released once libmecab no longer points to it
+        self.input = input;
+        self.features.clear();
     }
 
     pub fn size(&self) -> usize {
@@ -400,7 +444,14 @@ impl Lattice {
     }
 
     pub fn feature_constraint(&self, pos: u64) -> String {
-        unsafe { ptr_to_string(mecab_lattice_get_feature_constraint(self.inner, pos)) }
+        unsafe {
+            let ptr = mecab_lattice_get_feature_constraint(self.inner, pos);
+            if !ptr.is_null() {
+                ptr_to_string(ptr)
+            } else {
+                String::new()
+            }
+        }
     }
 
     pub fn set_boundary_constraint(&self, pos: u64, boundary_type: i32) {
@@ -409,16 +460,18 @@ impl Lattice {
         }
     }
 
-    pub fn set_feature_constraint<T: Into<Vec<u8>>>(&self,
+    pub fn set_feature_constraint<T: Into<Vec<u8>>>(&mut self,
                                                     begin_pos: u64,
                                                     end_pos: u64,
                                                     feature: T) {
+        let feature = CString::new(feature).unwrap().into_bytes_with_nul();
         unsafe {
             mecab_lattice_set_feature_constraint(self.inner,
                                                  begin_pos,
                                                  end_pos,
-                                                 str_to_ptr(&CString::new(feature).unwrap()));
+                                                 feature.as_ptr() as *const c_char);
         }
+        self.features.push(feature);
     }
 
     pub fn set_result<T: Into<Vec<u8>>>(&self, result: T) {
@@ -434,7 +487,6 @@ impl Drop for Lattice {
     fn drop(&mut self) {
         unsafe {
             mecab_lattice_destroy(self.inner);
-            self.free_input();
         }
     }
 }
@@ -457,6 +509,14 @@ impl Model {
         unsafe { Model { inner: mecab_model_new2(str_to_ptr(&CString::new(args).unwrap())) } }
     }
 
+    pub fn with_args<T: AsRef<str>>(args: &[T]) -> Model {
+        unsafe { Model { inner: new_with_args(args, mecab_model_new) } }
+    }
+
+    pub fn is_available(&self) -> bool {
+        !self.inner.is_null()
+    }
+
     pub fn create_tagger(&self) -> Tagger {
         unsafe {
             Tagger {
@@ -470,7 +530,8 @@ impl Model {
         unsafe {
             Lattice {
                 inner: mecab_model_new_lattice(self.inner),
-                input: ptr::null(),
+                input: Vec::new(),
+                features: Vec::new(),
             }
         }
     }
@@ -516,8 +577,8 @@ struct raw_node {
     next: *mut raw_node,
     enext: *mut raw_node,
     bnext: *mut raw_node,
-    rpath: *mut c_void,
-    lpath: *mut c_void,
+    rpath: *mut raw_path,
+    lpath: *mut raw_path,
     surface: *const c_char,
     feature: *const c_char,
     id: c_uint,
@@ -536,6 +597,16 @@ struct raw_node {
     cost: c_long,
 }
 
+#[repr(C)]
+struct raw_path {
+    rnode: *mut raw_node,
+    rnext: *mut raw_path,
+    lnode: *mut raw_node,
+    lnext: *mut raw_path,
+    cost: c_int,
+    prob: c_float,
+}
+
 enum Mode {
     NEXT,
     PREV,
@@ -683,6 +754,21 @@ impl Node {
             None
         }
     }
+
+    // cost of the edge from `left` to this node including the word cost,
+    // only recorded when the lattice was parsed with MECAB_NBEST or MECAB_MARGINAL_PROB
+    pub fn path_cost(&self, left: &Node) -> Option<i32> {
+        unsafe {
+            let mut path = (*self.inner).lpath;
+            while !path.is_null() {
+                if ptr::eq((*path).lnode, left.inner) {
+                    return Some((*path).cost);
+                }
+                path = (*path).lnext;
+            }
+            None
+        }
+    }
 }
 
 #[repr(C)]
@@ -756,6 +842,33 @@ impl DictionaryInfo {
     }
 }
 
+// argv[0] is skipped by libmecab like a program name
+unsafe fn new_with_args<T: AsRef<str>>(args: &[T],
+                                       new: unsafe extern "C" fn(c_int, *mut *mut c_char) -> *mut c_void)
+                                       -> *mut c_void {
+    let args = c_args("mecab", args).unwrap();
+    let mut argv = argv(&args);
+    new(argv.len() as c_int, argv.as_mut_ptr())
+}
+
+/// Arguments of a libmecab entry point, `program` first. Fails with the first
+/// argument that contains a NUL byte.
+pub(crate) fn c_args<I, T>(program: &str, args: I) -> Result<Vec<CString>, String>
+    where I: IntoIterator<Item = T>,
+          T: AsRef<str>
+{
+    Some(program.to_owned())
+        .into_iter()
+        .chain(args.into_iter().map(|a| a.as_ref().to_owned()))
+        .map(|a| CString::new(a.clone()).map_err(|_| a))
+        .collect()
+}
+
+/// `argv` pointing into `args`, which has to outlive it.
+pub(crate) fn argv(args: &[CString]) -> Vec<*mut c_char> {
+    args.iter().map(|a| a.as_ptr() as *mut c_char).collect()
+}
+
 fn str_to_ptr(input: &CString) -> *const c_char {
     input.as_ptr()
 }
@@ -772,3 +885,68 @@ fn ptr_to_string(ptr: *const c_char) -> String {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lattice_set_sentence() {
+        let mut lattice = Lattice::new();
+        lattice.set_sentence("太郎は本を読んだ");
+        assert_eq!(lattice.sentence(), "太郎は本を読んだ");
+        assert_eq!(lattice.size(), "太郎は本を読んだ".len());
+    }
+
+    #[test]
+    fn lattice_replace_sentence() {
+        let mut lattice = Lattice::new();
+        lattice.set_sentence("一つ目");
+        lattice.set_sentence("二つ目");
+        assert_eq!(lattice.sentence(), "二つ目");
+    }
+
+    #[test]
+    fn lattice_clear_set_drop() {
+        let mut lattice = Lattice::new();
+        lattice.set_sentence("花子に渡した");
+        lattice.clear();
+        assert_eq!(lattice.sentence(), "");
+        assert_eq!(lattice.size(), 0);
+        lattice.set_sentence("次郎が持っている");
+        assert_eq!(lattice.sentence(), "次郎が持っている");
+    }
+
+    #[test]
+    fn lattice_clear_twice_drop() {
+        let mut lattice = Lattice::new();
+        lattice.set_sentence("本");
+        lattice.clear();
+        lattice.clear();
+    }
+
+    #[test]
+    fn lattice_drop_without_sentence() {
+        let mut lattice = Lattice::new();
+        lattice.clear();
+    }
+
+    #[test]
+    fn lattice_feature_constraint_outlives_argument() {
+        let mut lattice = Lattice::new();
+        lattice.set_sentence("東京タワー");
+        lattice.set_feature_constraint(0, 6, String::from("名詞,固有名詞,*"));
+        assert_eq!(lattice.feature_constraint(0), "名詞,固有名詞,*");
+        assert_eq!(lattice.feature_constraint(6), "");
+    }
+
+    #[test]
+    fn lattice_constraints_reset_by_set_sentence() {
+        let mut lattice = Lattice::new();
+        lattice.set_sentence("東京タワー");
+        lattice.set_feature_constraint(0, 6, "名詞");
+        lattice.set_sentence("大阪");
+        assert!(!lattice.has_constraint());
+        assert_eq!(lattice.feature_constraint(0), "");
+    }
+}
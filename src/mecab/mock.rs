@@ -0,0 +1,497 @@
+// Rust stand-ins for the libmecab lattice functions. They follow the
+// ownership rules of libmecab, in particular the sentence and feature
+// constraints are only borrowed, so Miri reports any use after free.
+//
+// Models are built from a word list with `Model::mock` and their taggers
+// segment sentences by longest match, libmecab models cannot be loaded.
+
+use std::ffi::CString;
+use std::os::raw::*;
+use std::ptr;
+use std::slice;
+use std::str;
+
+use super::{raw_node, raw_path, size_t, Lattice, Model, Node, MECAB_ANY_BOUNDARY, MECAB_BOS_NODE, MECAB_EOS_NODE,
+            MECAB_NOR_NODE};
+
+struct MockLattice {
+    sentence: *const c_char,
+    size: size_t,
+    request_type: c_int,
+    theta: c_double,
+    z: c_double,
+    boundaries: Vec<c_int>,
+    features: Vec<*const c_char>,
+    // candidates set by `Lattice::mock_nodes`, freed when the lattice is cleared
+    nodes: Vec<*mut raw_node>,
+    paths: Vec<*mut raw_path>,
+    node_features: Vec<*mut c_char>,
+    begin: Vec<*mut raw_node>,
+    end: Vec<*mut raw_node>,
+}
+
+static EMPTY: &[u8] = b"\0";
+
+unsafe fn lattice<'a>(lattice: *mut c_void) -> &'a mut MockLattice {
+    &mut *(lattice as *mut MockLattice)
+}
+
+pub unsafe fn mecab_lattice_new() -> *mut c_void {
+    Box::into_raw(Box::new(MockLattice {
+        sentence: ptr::null(),
+        size: 0,
+        request_type: super::MECAB_ONE_BEST,
+        theta: 0.75,
+        z: 0.0,
+        boundaries: Vec::new(),
+        features: Vec::new(),
+        nodes: Vec::new(),
+        paths: Vec::new(),
+        node_features: Vec::new(),
+        begin: Vec::new(),
+        end: Vec::new(),
+    })) as *mut c_void
+}
+
+pub unsafe fn mecab_lattice_destroy(l: *mut c_void) {
+    mecab_lattice_clear(l);
+    drop(Box::from_raw(l as *mut MockLattice));
+}
+
+pub unsafe fn mecab_lattice_clear(l: *mut c_void) {
+    let l = lattice(l);
+    l.sentence = ptr::null();
+    l.size = 0;
+    l.z = 0.0;
+    l.boundaries.clear();
+    l.features.clear();
+    for node in l.nodes.drain(..) {
+        drop(Box::from_raw(node));
+    }
+    for path in l.paths.drain(..) {
+        drop(Box::from_raw(path));
+    }
+    for feature in l.node_features.drain(..) {
+        drop(CString::from_raw(feature));
+    }
+    l.begin.clear();
+    l.end.clear();
+}
+
+pub unsafe fn mecab_lattice_is_available(l: *mut c_void) -> c_int {
+    !lattice(l).sentence.is_null() as c_int
+}
+
+pub unsafe fn mecab_lattice_get_bos_node(l: *mut c_void) -> *mut raw_node {
+    lattice(l).nodes.first().cloned().unwrap_or(ptr::null_mut())
+}
+
+pub unsafe fn mecab_lattice_get_eos_node(l: *mut c_void) -> *mut raw_node {
+    lattice(l).nodes.last().cloned().unwrap_or(ptr::null_mut())
+}
+
+pub unsafe fn mecab_lattice_get_begin_nodes(l: *mut c_void, pos: size_t) -> *const raw_node {
+    *lattice(l).begin.get(pos).unwrap_or(&ptr::null_mut())
+}
+
+pub unsafe fn mecab_lattice_get_end_nodes(l: *mut c_void, pos: size_t) -> *const raw_node {
+    *lattice(l).end.get(pos).unwrap_or(&ptr::null_mut())
+}
+
+pub unsafe fn mecab_lattice_get_sentence(l: *mut c_void) -> *const c_char {
+    let l = lattice(l);
+    if !l.sentence.is_null() {
+        // touch the whole sentence so that a dangling buffer is noticed right away
+        let _ = ptr::read_volatile(l.sentence.add(l.size));
+    }
+    l.sentence
+}
+
+pub unsafe fn mecab_lattice_set_sentence2(l: *mut c_void, sentence: *const c_char, len: size_t) {
+    mecab_lattice_clear(l);
+    let l = lattice(l);
+    l.sentence = sentence;
+    l.size = len;
+}
+
+pub unsafe fn mecab_lattice_get_size(l: *mut c_void) -> size_t {
+    lattice(l).size
+}
+
+pub unsafe fn mecab_lattice_get_z(l: *mut c_void) -> c_double {
+    lattice(l).z
+}
+
+pub unsafe fn mecab_lattice_set_z(l: *mut c_void, z: c_double) {
+    lattice(l).z = z;
+}
+
+pub unsafe fn mecab_lattice_get_theta(l: *mut c_void) -> c_double {
+    lattice(l).theta
+}
+
+pub unsafe fn mecab_lattice_set_theta(l: *mut c_void, theta: c_double) {
+    lattice(l).theta = theta;
+}
+
+pub unsafe fn mecab_lattice_next(_: *mut c_void) -> c_int {
+    0
+}
+
+pub unsafe fn mecab_lattice_get_request_type(l: *mut c_void) -> c_int {
+    lattice(l).request_type
+}
+
+pub unsafe fn mecab_lattice_has_request_type(l: *mut c_void, request_type: c_int) -> c_int {
+    (lattice(l).request_type & request_type != 0) as c_int
+}
+
+pub unsafe fn mecab_lattice_set_request_type(l: *mut c_void, request_type: c_int) {
+    lattice(l).request_type = request_type;
+}
+
+pub unsafe fn mecab_lattice_add_request_type(l: *mut c_void, request_type: c_int) {
+    lattice(l).request_type |= request_type;
+}
+
+pub unsafe fn mecab_lattice_remove_request_type(l: *mut c_void, request_type: c_int) {
+    lattice(l).request_type &= !request_type;
+}
+
+pub unsafe fn mecab_lattice_tostr(_: *mut c_void) -> *const c_char {
+    EMPTY.as_ptr() as *const c_char
+}
+
+pub unsafe fn mecab_lattice_nbest_tostr(_: *mut c_void, _: i64) -> *const c_char {
+    EMPTY.as_ptr() as *const c_char
+}
+
+pub unsafe fn mecab_lattice_has_constraint(l: *mut c_void) -> c_int {
+    let l = lattice(l);
+    (!l.boundaries.is_empty() || !l.features.is_empty()) as c_int
+}
+
+pub unsafe fn mecab_lattice_get_boundary_constraint(l: *mut c_void, pos: u64) -> c_int {
+    *lattice(l).boundaries.get(pos as usize).unwrap_or(&MECAB_ANY_BOUNDARY)
+}
+
+pub unsafe fn mecab_lattice_get_feature_constraint(l: *mut c_void, pos: u64) -> *const c_char {
+    *lattice(l).features.get(pos as usize).unwrap_or(&ptr::null())
+}
+
+pub unsafe fn mecab_lattice_set_boundary_constraint(l: *mut c_void, pos: u64, boundary_type: i32) {
+    let l = lattice(l);
+    if l.boundaries.is_empty() {
+        l.boundaries.resize(l.size + 4, MECAB_ANY_BOUNDARY);
+    }
+    l.boundaries[pos as usize] = boundary_type;
+}
+
+pub unsafe fn mecab_lattice_set_feature_constraint(l: *mut c_void,
+                                                   begin_pos: u64,
+                                                   end_pos: u64,
+                                                   feature: *const c_char) {
+    if begin_pos >= end_pos || feature.is_null() {
+        return;
+    }
+    let l = lattice(l);
+    if l.features.is_empty() {
+        l.features.resize(l.size + 4, ptr::null());
+    }
+    l.features[begin_pos as usize] = feature;
+}
+
+pub unsafe fn mecab_lattice_set_result(_: *mut c_void, _: *const c_char) {}
+
+pub unsafe fn mecab_lattice_strerror(_: *mut c_void) -> *const c_char {
+    EMPTY.as_ptr() as *const c_char
+}
+
+impl Node {
+    // a node outside of any lattice, `surface` runs until the end of the sentence as in libmecab
+    pub(crate) fn mock(surface: &str, length: u16, feature: &str) -> Node {
+        Node {
+            inner: ptr::null(),
+            prev: ptr::null_mut(),
+            next: ptr::null_mut(),
+            enext: ptr::null_mut(),
+            bnext: ptr::null_mut(),
+            surface: surface.to_owned(),
+            feature: feature.to_owned(),
+            id: 0,
+            length,
+            rlength: length,
+            rcattr: 0,
+            lcattr: 0,
+            posid: 0,
+            char_type: 0,
+            stat: MECAB_NOR_NODE as u8,
+            isbest: true,
+            alpha: 0.0,
+            beta: 0.0,
+            prob: 0.0,
+            wcost: 0,
+            cost: 0,
+        }
+    }
+}
+
+/// Candidate morpheme of a mock lattice, see `Lattice::mock_nodes`.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct MockNode<'a> {
+    /// Byte offset and length of the surface within the sentence.
+    pub begin: usize,
+    pub length: usize,
+    /// White space skipped before the surface.
+    pub space: usize,
+    pub feature: &'a str,
+    pub wcost: i16,
+    /// Left and right context id.
+    pub attr: u16,
+    /// Marginal probability.
+    pub prob: f32,
+}
+
+impl<'a> MockNode<'a> {
+    pub(crate) fn new(begin: usize, length: usize, feature: &'a str, wcost: i16) -> MockNode<'a> {
+        MockNode {
+            begin,
+            length,
+            space: 0,
+            feature,
+            wcost,
+            attr: 0,
+            prob: 0.0,
+        }
+    }
+
+    pub(crate) fn attr(mut self, attr: u16) -> MockNode<'a> {
+        self.attr = attr;
+        self
+    }
+
+    pub(crate) fn prob(mut self, prob: f32) -> MockNode<'a> {
+        self.prob = prob;
+        self
+    }
+}
+
+impl MockLattice {
+    // appends a node to the begin and end lists, BOS only ends and EOS only begins
+    unsafe fn push_node(&mut self, node: &MockNode, stat: i32) -> *mut raw_node {
+        let feature = CString::new(node.feature).unwrap().into_raw();
+        let raw = Box::into_raw(Box::new(raw_node {
+            prev: ptr::null_mut(),
+            next: ptr::null_mut(),
+            enext: ptr::null_mut(),
+            bnext: ptr::null_mut(),
+            rpath: ptr::null_mut(),
+            lpath: ptr::null_mut(),
+            surface: self.sentence.add(node.begin),
+            feature,
+            id: self.nodes.len() as c_uint,
+            length: node.length as c_ushort,
+            rlength: (node.space + node.length) as c_ushort,
+            rcattr: node.attr,
+            lcattr: node.attr,
+            posid: 0,
+            char_type: 0,
+            stat: stat as c_uchar,
+            isbest: 0,
+            alpha: 0.0,
+            beta: 0.0,
+            prob: node.prob,
+            wcost: node.wcost,
+            cost: if stat == MECAB_BOS_NODE { 0 } else { c_long::MAX },
+        }));
+        self.node_features.push(feature);
+        self.nodes.push(raw);
+
+        if stat != MECAB_BOS_NODE {
+            append(&mut self.begin[node.begin - node.space], raw, |n| &mut (*n).bnext);
+        }
+        if stat != MECAB_EOS_NODE {
+            append(&mut self.end[node.begin + node.length], raw, |n| &mut (*n).enext);
+        }
+        raw
+    }
+}
+
+unsafe fn append<F: Fn(*mut raw_node) -> *mut *mut raw_node>(head: &mut *mut raw_node, node: *mut raw_node, next: F) {
+    let mut slot: *mut *mut raw_node = head;
+    while !(*slot).is_null() {
+        slot = next(*slot);
+    }
+    *slot = node;
+}
+
+impl Lattice {
+    // replaces the candidates of the sentence with `nodes` between BOS and EOS and links the
+    // cheapest path like the viterbi search of libmecab, `connection` takes the right context
+    // id of the left node and the left context id of the right node
+    pub(crate) fn mock_nodes<F: Fn(u16, u16) -> i32>(&mut self, nodes: &[MockNode], connection: F) {
+        unsafe {
+            set_nodes(self.inner, nodes, connection);
+        }
+    }
+}
+
+unsafe fn set_nodes<F: Fn(u16, u16) -> i32>(l: *mut c_void, nodes: &[MockNode], connection: F) {
+    let (sentence, size) = (lattice(l).sentence, lattice(l).size);
+    mecab_lattice_clear(l);
+    let l = lattice(l);
+    l.sentence = sentence;
+    l.size = size;
+    l.begin = vec![ptr::null_mut(); size + 1];
+    l.end = vec![ptr::null_mut(); size + 1];
+
+    let boundary = "BOS/EOS,*,*,*,*,*,*,*,*";
+    l.push_node(&MockNode::new(0, 0, boundary, 0), MECAB_BOS_NODE);
+    for node in nodes {
+        l.push_node(node, MECAB_NOR_NODE);
+    }
+    // like libmecab EOS connects to the last nodes, trailing white space counts as its own
+    let last = nodes.iter().map(|n| n.begin + n.length).max().unwrap_or(0);
+    let mut eos = MockNode::new(size, 0, boundary, 0);
+    eos.space = size - last;
+    let eos = l.push_node(&eos, MECAB_EOS_NODE);
+
+    for pos in 0..size + 1 {
+        let mut right = l.begin[pos];
+        while !right.is_null() {
+            let mut left = l.end[pos];
+            while !left.is_null() {
+                if (*left).cost != c_long::MAX {
+                    let cost = connection((*left).rcattr, (*right).lcattr) + (*right).wcost as i32;
+                    let path = Box::into_raw(Box::new(raw_path {
+                        rnode: right,
+                        rnext: (*left).rpath,
+                        lnode: left,
+                        lnext: (*right).lpath,
+                        cost,
+                        prob: 0.0,
+                    }));
+                    l.paths.push(path);
+                    (*left).rpath = path;
+                    (*right).lpath = path;
+                    if (*left).cost + (cost as c_long) < (*right).cost {
+                        (*right).cost = (*left).cost + cost as c_long;
+                        (*right).prev = left;
+                    }
+                }
+                left = (*left).enext;
+            }
+            right = (*right).bnext;
+        }
+    }
+
+    let mut node = eos;
+    while !node.is_null() {
+        (*node).isbest = 1;
+        let prev = (*node).prev;
+        if !prev.is_null() {
+            (*prev).next = node;
+        }
+        node = prev;
+    }
+}
+
+struct MockModel {
+    // surfaces and features of the dictionary
+    words: Vec<(String, String)>,
+}
+
+struct MockTagger {
+    model: *const MockModel,
+    theta: c_float,
+}
+
+// libmecab fails to load without a dictionary
+pub unsafe extern "C" fn mecab_new(_: c_int, _: *mut *mut c_char) -> *mut c_void {
+    ptr::null_mut()
+}
+
+pub unsafe fn mecab_new2(_: *const c_char) -> *mut c_void {
+    ptr::null_mut()
+}
+
+pub unsafe fn mecab_destroy(mecab: *mut c_void) {
+    if !mecab.is_null() {
+        drop(Box::from_raw(mecab as *mut MockTagger));
+    }
+}
+
+pub unsafe fn mecab_get_theta(mecab: *mut c_void) -> c_float {
+    (*(mecab as *mut MockTagger)).theta
+}
+
+pub unsafe fn mecab_set_theta(mecab: *mut c_void, theta: c_float) {
+    (*(mecab as *mut MockTagger)).theta = theta;
+}
+
+// splits the sentence into the longest words of the model, white space is skipped and
+// counted in the `rlength` of the next node like libmecab does, characters without a
+// word become nouns of their own
+pub unsafe fn mecab_parse_lattice(mecab: *mut c_void, l: *mut c_void) -> c_int {
+    let (sentence, size) = (lattice(l).sentence, lattice(l).size);
+    if sentence.is_null() {
+        return 0;
+    }
+    let sentence = str::from_utf8(slice::from_raw_parts(sentence as *const u8, size)).unwrap();
+    let words = &(*(*(mecab as *mut MockTagger)).model).words;
+
+    let mut nodes = Vec::new();
+    let mut pos = 0;
+    let mut space = 0;
+    while let Some(c) = sentence[pos..].chars().next() {
+        if c.is_whitespace() {
+            pos += c.len_utf8();
+            space += c.len_utf8();
+            continue;
+        }
+        let mut node = words.iter()
+            .filter(|&(surface, _)| !surface.is_empty() && sentence[pos..].starts_with(surface.as_str()))
+            .max_by_key(|&(surface, _)| surface.len())
+            .map_or(MockNode::new(pos, c.len_utf8(), "名詞,一般,*,*,*,*,*", 0),
+                    |(surface, feature)| MockNode::new(pos, surface.len(), feature, 0));
+        node.space = space;
+        pos += node.length;
+        space = 0;
+        nodes.push(node);
+    }
+    set_nodes(l, &nodes, |_, _| 0);
+    1
+}
+
+pub unsafe extern "C" fn mecab_model_new(_: c_int, _: *mut *mut c_char) -> *mut c_void {
+    ptr::null_mut()
+}
+
+pub unsafe fn mecab_model_new2(_: *const c_char) -> *mut c_void {
+    ptr::null_mut()
+}
+
+pub unsafe fn mecab_model_destroy(model: *mut c_void) {
+    if !model.is_null() {
+        drop(Box::from_raw(model as *mut MockModel));
+    }
+}
+
+pub unsafe fn mecab_model_new_tagger(model: *mut c_void) -> *mut c_void {
+    Box::into_raw(Box::new(MockTagger {
+        model: model as *const MockModel,
+        theta: 0.75,
+    })) as *mut c_void
+}
+
+pub unsafe fn mecab_model_new_lattice(_: *mut c_void) -> *mut c_void {
+    mecab_lattice_new()
+}
+
+impl Model {
+    // model whose taggers know only `words`, pairs of a surface and its feature
+    pub(crate) fn mock(words: &[(&str, &str)]) -> Model {
+        let words = words.iter().map(|&(surface, feature)| (surface.to_owned(), feature.to_owned())).collect();
+        Model { inner: Box::into_raw(Box::new(MockModel { words })) as *mut c_void }
+    }
+}
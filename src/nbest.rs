@@ -0,0 +1,167 @@
+use std::collections::HashSet;
+use std::ffi::CString;
+
+use mecab::{Lattice, Node, Tagger, MECAB_EOS_NODE, MECAB_MARGINAL_PROB, MECAB_NBEST};
+use offset::OffsetMap;
+use token::{is_boundary_node, long_to_i64, Span, Token};
+
+#[cfg(feature = "json")]
+use serde::{Deserialize, Serialize};
+
+/// One segmentation of a sentence.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "json", derive(Serialize, Deserialize))]
+pub struct Path {
+    pub tokens: Vec<Token>,
+    /// Sum of word and connection costs from BOS to EOS.
+    pub cost: i64,
+    /// Probability of the path, only set when marginal probabilities were requested.
+    pub prob: Option<f64>,
+}
+
+impl Path {
+    /// Builds the path currently linked from the lattice BOS node.
+    pub fn from_lattice(lattice: &Lattice) -> Path {
+        let offsets = OffsetMap::new(&lattice.sentence());
+        let mut tokens = Vec::new();
+        let mut cost = 0;
+        let mut prev: Option<Node> = None;
+
+        for node in lattice.bos_node().iter_next() {
+            if let Some(ref left) = prev {
+                // fall back to the viterbi cost if the lattice keeps no paths
+                cost += match node.path_cost(left) {
+                    Some(c) => c as i64,
+                    None => long_to_i64(node.cost) - long_to_i64(left.cost),
+                };
+            }
+            if !is_boundary_node(&node) {
+                tokens.push(Token::from_node(&node, &offsets));
+            }
+            let eos = node.stat as i32 == MECAB_EOS_NODE;
+            prev = Some(node);
+            if eos {
+                break;
+            }
+        }
+
+        let prob = if lattice.has_request_type(MECAB_MARGINAL_PROB) {
+            Some((-lattice.theta() * cost as f64 - lattice.z()).exp())
+        } else {
+            None
+        };
+
+        Path { tokens, cost, prob }
+    }
+
+    pub fn spans(&self) -> Vec<Span> {
+        self.tokens.iter().map(|t| t.span).collect()
+    }
+}
+
+/// Iterator over the n best paths of a sentence.
+///
+/// All state lives in the iterator and its lattice, so several n-best
+/// enumerations can run on the same tagger at once.
+pub struct NBest<'a> {
+    tagger: &'a Tagger,
+    lattice: LatticeSlot<'a>,
+    input: Option<CString>,
+    remaining: usize,
+    parsed: bool,
+    dedup: Option<HashSet<Vec<Span>>>,
+}
+
+enum LatticeSlot<'a> {
+    Owned(Lattice),
+    Borrowed(&'a mut Lattice),
+}
+
+impl<'a> NBest<'a> {
+    /// Skips paths whose segmentation equals an earlier path and only differs in features.
+    pub fn dedup(mut self, dedup: bool) -> NBest<'a> {
+        self.dedup = if dedup { Some(HashSet::new()) } else { None };
+        self
+    }
+
+    /// Computes path probabilities with the given temperature.
+    pub fn with_probability(self, theta: f64) -> NBest<'a> {
+        self.lattice().add_request_type(MECAB_MARGINAL_PROB);
+        self.lattice().set_theta(theta);
+        self
+    }
+
+    pub fn lattice(&self) -> &Lattice {
+        match self.lattice {
+            LatticeSlot::Owned(ref lattice) => lattice,
+            LatticeSlot::Borrowed(ref lattice) => lattice,
+        }
+    }
+
+    fn advance(&mut self) -> bool {
+        if !self.parsed {
+            self.parsed = true;
+            if let Some(input) = self.input.take() {
+                if let LatticeSlot::Owned(ref mut lattice) = self.lattice {
+                    lattice.set_sentence(input);
+                }
+            }
+            // the first `next` after parsing yields the viterbi path again,
+            // so every path is taken from `next`
+            if !self.tagger.parse(self.lattice()) {
+                return false;
+            }
+        }
+        self.lattice().next()
+    }
+}
+
+impl<'a> Iterator for NBest<'a> {
+    type Item = Path;
+
+    fn next(&mut self) -> Option<Path> {
+        while self.remaining > 0 && self.advance() {
+            let path = Path::from_lattice(self.lattice());
+            if let Some(ref mut seen) = self.dedup {
+                if !seen.insert(path.spans()) {
+                    continue;
+                }
+            }
+            self.remaining -= 1;
+            return Some(path);
+        }
+        self.remaining = 0;
+        None
+    }
+}
+
+impl Tagger {
+    pub fn nbest<T: Into<Vec<u8>>>(&self, input: T, n: usize) -> NBest<'_> {
+        let lattice = Lattice::new();
+        lattice.set_request_type(MECAB_NBEST);
+
+        NBest {
+            tagger: self,
+            lattice: LatticeSlot::Owned(lattice),
+            input: Some(CString::new(input).unwrap()),
+            remaining: n,
+            parsed: false,
+            dedup: None,
+        }
+    }
+
+    /// Like `nbest` for the sentence already set on `lattice`, keeping its
+    /// theta, request type and constraints. `MECAB_NBEST` is added to the request type.
+    pub fn nbest_lattice<'a>(&'a self, lattice: &'a mut Lattice, n: usize) -> NBest<'a> {
+        lattice.add_request_type(MECAB_NBEST);
+
+        NBest {
+            tagger: self,
+            lattice: LatticeSlot::Borrowed(lattice),
+            input: None,
+            remaining: n,
+            parsed: false,
+            dedup: None,
+        }
+    }
+}
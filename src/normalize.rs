@@ -0,0 +1,263 @@
+use unicode_normalization::UnicodeNormalization;
+
+use kana::is_kana;
+use mecab::{Lattice, Tagger};
+use offset::OffsetMap;
+use token::{Span, Token};
+
+const HALFWIDTH_KATAKANA: &str = "ヲァィゥェォャュョッーアイウエオカキクケコサシスセソタチツテトナニヌネノハヒフヘホマミムメモヤユヨラリルレロワン";
+
+const PROLONGED_MARKS: &[char] = &['ｰ', '‐', '‑', '–', '—', '―', '─', '━', '−', '－'];
+const WAVE_DASHES: &[char] = &['∼', '∾', '〰', '～'];
+
+/// Configurable text normalization that runs before `Lattice::set_sentence`.
+///
+/// Every normalizer is disabled by default.
+#[derive(Clone, Debug, Default)]
+pub struct Normalizer {
+    nfkc: bool,
+    fold_width: bool,
+    unify_prolonged: bool,
+    collapse_repeats: Option<usize>,
+}
+
+impl Normalizer {
+    pub fn new() -> Normalizer {
+        Normalizer::default()
+    }
+
+    /// Applies Unicode NFKC to every char together with its combining marks.
+    pub fn nfkc(mut self, enable: bool) -> Normalizer {
+        self.nfkc = enable;
+        self
+    }
+
+    /// Maps full-width ASCII to ASCII and half-width katakana to full-width katakana.
+    pub fn fold_width(mut self, enable: bool) -> Normalizer {
+        self.fold_width = enable;
+        self
+    }
+
+    /// Replaces dash-like chars following kana with `ー` and wave dash variants with `〜`.
+    pub fn unify_prolonged(mut self, enable: bool) -> Normalizer {
+        self.unify_prolonged = enable;
+        self
+    }
+
+    /// Shortens runs of the same char to at most `max` chars.
+    pub fn collapse_repeats(mut self, max: Option<usize>) -> Normalizer {
+        self.collapse_repeats = max.map(|m| m.max(1));
+        self
+    }
+
+    pub fn normalize(&self, text: &str) -> Normalized {
+        let mut out = String::with_capacity(text.len());
+        let mut pieces: Vec<Piece> = Vec::new();
+        let mut prev_kana = false;
+
+        for (begin, group) in groups(text) {
+            let end = begin + group.len();
+            let mut chunk: String = group.to_owned();
+
+            if self.unify_prolonged {
+                let first = group.chars().next().unwrap();
+                if prev_kana && PROLONGED_MARKS.contains(&first) {
+                    chunk = "ー".to_owned();
+                } else if WAVE_DASHES.contains(&first) {
+                    chunk = "〜".to_owned();
+                }
+            }
+            if self.nfkc {
+                chunk = chunk.nfkc().collect();
+            }
+            if self.fold_width {
+                chunk = chunk.chars().map(fold_width).collect();
+                if chunk.chars().count() > 1 {
+                    // merge folded voicing marks into their kana
+                    chunk = chunk.nfc().collect();
+                }
+            }
+            prev_kana = chunk.chars().last().is_some_and(is_kana);
+
+            for c in chunk.chars() {
+                if let Some(max) = self.collapse_repeats {
+                    let run = out.chars().rev().take_while(|&p| p == c).take(max).count();
+                    if run == max {
+                        // the dropped char is attributed to the char it repeats
+                        pieces.last_mut().unwrap().original.end = end;
+                        continue;
+                    }
+                }
+                pieces.push(Piece {
+                    normalized: out.len(),
+                    original: Span::new(begin, end),
+                });
+                out.push(c);
+            }
+        }
+
+        Normalized {
+            text: out,
+            original_len: text.len(),
+            pieces,
+        }
+    }
+
+    /// Normalizes `text`, parses it and maps the token spans back to `text`.
+    ///
+    /// Token surfaces keep the normalized form. Returns `None` if parsing fails.
+    pub fn parse(&self, tagger: &Tagger, lattice: &mut Lattice, text: &str) -> Option<Vec<Token>> {
+        let normalized = self.normalize(text);
+        lattice.set_sentence(normalized.text.as_str());
+        if !tagger.parse(lattice) {
+            return None;
+        }
+
+        let offsets = OffsetMap::new(text);
+        Some(lattice.tokens()
+            .into_iter()
+            .map(|mut token| {
+                token.span = normalized.span_to_original(token.span);
+                token.char_span = offsets.byte_span_to_char(token.span).unwrap();
+                token.utf16_span = offsets.byte_span_to_utf16(token.span).unwrap();
+                token
+            })
+            .collect())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Piece {
+    // byte offset of a normalized char and the original bytes it came from
+    normalized: usize,
+    original: Span,
+}
+
+/// Normalized text with its alignment to the original text.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Normalized {
+    pub text: String,
+    original_len: usize,
+    pieces: Vec<Piece>,
+}
+
+impl Normalized {
+    /// Original byte offset where the normalized char at byte `offset` starts.
+    pub fn to_original(&self, offset: usize) -> usize {
+        match self.piece(offset) {
+            Some(piece) => piece.original.begin,
+            None => self.original_len,
+        }
+    }
+
+    /// Maps a normalized byte span to the smallest original span covering it.
+    pub fn span_to_original(&self, span: Span) -> Span {
+        let begin = self.to_original(span.begin);
+        if span.is_empty() {
+            return Span::new(begin, begin);
+        }
+        let end = match self.piece(span.end - 1) {
+            Some(piece) => piece.original.end,
+            None => self.original_len,
+        };
+        Span::new(begin, end)
+    }
+
+    fn piece(&self, offset: usize) -> Option<&Piece> {
+        if offset >= self.text.len() {
+            return None;
+        }
+        let i = match self.pieces.binary_search_by_key(&offset, |p| p.normalized) {
+            Ok(i) => i,
+            Err(i) => i - 1,
+        };
+        self.pieces.get(i)
+    }
+}
+
+// splits text into chars followed by their combining or voicing marks
+fn groups(text: &str) -> Vec<(usize, &str)> {
+    let mut groups = Vec::new();
+    let mut start = None;
+
+    for (i, c) in text.char_indices() {
+        if start.is_some() && is_combining(c) {
+            continue;
+        }
+        if let Some(s) = start {
+            groups.push((s, &text[s..i]));
+        }
+        start = Some(i);
+    }
+    if let Some(s) = start {
+        groups.push((s, &text[s..]));
+    }
+
+    groups
+}
+
+fn is_combining(c: char) -> bool {
+    matches!(c, '\u{0300}'..='\u{036F}' | '\u{3099}' | '\u{309A}' | '\u{FF9E}' | '\u{FF9F}')
+}
+
+fn fold_width(c: char) -> char {
+    match c {
+        '\u{FF01}'..='\u{FF5E}' => ::std::char::from_u32(c as u32 - 0xFEE0).unwrap(),
+        '\u{3000}' => ' ',
+        '｡' => '。',
+        '｢' => '「',
+        '｣' => '」',
+        '､' => '、',
+        '･' => '・',
+        '\u{FF9E}' => '\u{3099}',
+        '\u{FF9F}' => '\u{309A}',
+        '\u{FF66}'..='\u{FF9D}' => HALFWIDTH_KATAKANA.chars().nth(c as usize - 0xFF66).unwrap(),
+        _ => c,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disabled_by_default() {
+        let normalized = Normalizer::new().normalize("ＡＢＣ　ｶﾞｰ");
+        assert_eq!(normalized.text, "ＡＢＣ　ｶﾞｰ");
+    }
+
+    #[test]
+    fn fold_width() {
+        let normalized = Normalizer::new().fold_width(true).normalize("ＡＢＣ　ｶﾞｷﾞ｡");
+        assert_eq!(normalized.text, "ABC ガギ。");
+        // ｶﾞ is two original chars folded into one
+        assert_eq!(normalized.span_to_original(Span::new(4, 7)), Span::new(12, 18));
+        assert_eq!(normalized.to_original(7), 18);
+    }
+
+    #[test]
+    fn nfkc() {
+        let normalized = Normalizer::new().nfkc(true).normalize("①㍻ﾊﾟ");
+        assert_eq!(normalized.text, "1平成パ");
+        assert_eq!(normalized.span_to_original(Span::new(1, 7)), Span::new(3, 6));
+    }
+
+    #[test]
+    fn unify_prolonged() {
+        let normalizer = Normalizer::new().unify_prolonged(true);
+        assert_eq!(normalizer.normalize("ラーメン−スープ").text, "ラーメンースープ");
+        // dashes after other chars are kept
+        assert_eq!(normalizer.normalize("1−2").text, "1−2");
+        assert_eq!(normalizer.normalize("東京～大阪").text, "東京〜大阪");
+    }
+
+    #[test]
+    fn collapse_repeats() {
+        let normalized = Normalizer::new().collapse_repeats(Some(2)).normalize("すごーーーい");
+        assert_eq!(normalized.text, "すごーーい");
+        // the dropped ー is attributed to the last kept one
+        assert_eq!(normalized.span_to_original(Span::new(9, 12)), Span::new(9, 15));
+        assert_eq!(normalized.to_original(12), 15);
+        assert_eq!(normalized.span_to_original(Span::new(15, 15)), Span::new(18, 18));
+    }
+}
@@ -0,0 +1,98 @@
+use token::Span;
+
+/// Converts offsets of one sentence between bytes, chars and UTF-16 code units.
+///
+/// Conversions from chars are O(1), conversions to chars are O(log n).
+/// Offsets that do not fall on a char boundary have no counterpart and
+/// yield `None`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OffsetMap {
+    // byte and UTF-16 offset of every char followed by the sentence length
+    bytes: Vec<usize>,
+    utf16: Vec<usize>,
+}
+
+impl OffsetMap {
+    pub fn new(text: &str) -> OffsetMap {
+        let mut bytes = Vec::with_capacity(text.len() + 1);
+        let mut utf16 = Vec::with_capacity(text.len() + 1);
+        let mut units = 0;
+
+        for (i, c) in text.char_indices() {
+            bytes.push(i);
+            utf16.push(units);
+            units += c.len_utf16();
+        }
+        bytes.push(text.len());
+        utf16.push(units);
+
+        OffsetMap { bytes, utf16 }
+    }
+
+    pub fn byte_len(&self) -> usize {
+        self.bytes[self.bytes.len() - 1]
+    }
+
+    pub fn char_len(&self) -> usize {
+        self.bytes.len() - 1
+    }
+
+    pub fn utf16_len(&self) -> usize {
+        self.utf16[self.utf16.len() - 1]
+    }
+
+    pub fn char_to_byte(&self, offset: usize) -> Option<usize> {
+        self.bytes.get(offset).cloned()
+    }
+
+    pub fn char_to_utf16(&self, offset: usize) -> Option<usize> {
+        self.utf16.get(offset).cloned()
+    }
+
+    pub fn byte_to_char(&self, offset: usize) -> Option<usize> {
+        self.bytes.binary_search(&offset).ok()
+    }
+
+    pub fn utf16_to_char(&self, offset: usize) -> Option<usize> {
+        self.utf16.binary_search(&offset).ok()
+    }
+
+    pub fn byte_to_utf16(&self, offset: usize) -> Option<usize> {
+        self.byte_to_char(offset).and_then(|c| self.char_to_utf16(c))
+    }
+
+    pub fn utf16_to_byte(&self, offset: usize) -> Option<usize> {
+        self.utf16_to_char(offset).and_then(|c| self.char_to_byte(c))
+    }
+
+    pub fn byte_span_to_char(&self, span: Span) -> Option<Span> {
+        map_span(span, |o| self.byte_to_char(o))
+    }
+
+    pub fn byte_span_to_utf16(&self, span: Span) -> Option<Span> {
+        map_span(span, |o| self.byte_to_utf16(o))
+    }
+
+    pub fn char_span_to_byte(&self, span: Span) -> Option<Span> {
+        map_span(span, |o| self.char_to_byte(o))
+    }
+
+    pub fn char_span_to_utf16(&self, span: Span) -> Option<Span> {
+        map_span(span, |o| self.char_to_utf16(o))
+    }
+
+    pub fn utf16_span_to_byte(&self, span: Span) -> Option<Span> {
+        map_span(span, |o| self.utf16_to_byte(o))
+    }
+
+    pub fn utf16_span_to_char(&self, span: Span) -> Option<Span> {
+        map_span(span, |o| self.utf16_to_char(o))
+    }
+}
+
+fn map_span<F: Fn(usize) -> Option<usize>>(span: Span, f: F) -> Option<Span> {
+    match (f(span.begin), f(span.end)) {
+        (Some(begin), Some(end)) => Some(Span::new(begin, end)),
+        _ => None,
+    }
+}
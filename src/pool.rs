@@ -0,0 +1,178 @@
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+
+use mecab::{Lattice, Model, Tagger, MECAB_ONE_BEST};
+use nbest::NBest;
+use token::Token;
+
+/// Taggers and lattices of a shared model, reused across threads.
+///
+/// A tagger is only ever used by the thread that checked it out.
+pub struct TaggerPool {
+    // declared before the model so that idle taggers are dropped first
+    idle: Mutex<Vec<(Tagger, Lattice)>>,
+    model: Arc<Model>,
+}
+
+impl TaggerPool {
+    pub fn new(model: Model) -> TaggerPool {
+        TaggerPool::with_model(Arc::new(model))
+    }
+
+    pub fn with_model(model: Arc<Model>) -> TaggerPool {
+        TaggerPool {
+            idle: Mutex::new(Vec::new()),
+            model,
+        }
+    }
+
+    pub fn model(&self) -> &Model {
+        &self.model
+    }
+
+    /// Checks out an idle tagger or creates a new one, it returns to the pool when dropped
+    /// with its lattice cleared and the request type and theta reset.
+    pub fn get(&self) -> PooledTagger<'_> {
+        let idle = self.idle.lock().unwrap().pop();
+        let (tagger, lattice) = idle.unwrap_or_else(|| (self.model.create_tagger(), self.model.create_lattice()));
+        PooledTagger {
+            pool: self,
+            // taggers are returned reset, so whatever they have now is the default
+            theta: (tagger.theta(), lattice.theta()),
+            tagger: Some(tagger),
+            lattice: Some(lattice),
+        }
+    }
+
+    /// Parses `input` with a pooled tagger, returns `None` if parsing fails.
+    pub fn parse_to_tokens<T: Into<Vec<u8>>>(&self, input: T) -> Option<Vec<Token>> {
+        let mut tagger = self.get();
+        tagger.lattice_mut().set_sentence(input);
+        if tagger.parse(tagger.lattice()) {
+            Some(tagger.lattice().tokens())
+        } else {
+            None
+        }
+    }
+}
+
+/// A tagger and lattice checked out of a `TaggerPool`, dereferences to the tagger.
+pub struct PooledTagger<'a> {
+    pool: &'a TaggerPool,
+    theta: (f32, f64),
+    tagger: Option<Tagger>,
+    lattice: Option<Lattice>,
+}
+
+impl<'a> PooledTagger<'a> {
+    pub fn lattice(&self) -> &Lattice {
+        self.lattice.as_ref().unwrap()
+    }
+
+    pub fn lattice_mut(&mut self) -> &mut Lattice {
+        self.lattice.as_mut().unwrap()
+    }
+
+    /// N-best paths of the sentence set on the lattice, see `Tagger::nbest_lattice`.
+    pub fn nbest(&mut self, n: usize) -> NBest<'_> {
+        let (tagger, lattice) = (self.tagger.as_ref().unwrap(), self.lattice.as_mut().unwrap());
+        tagger.nbest_lattice(lattice, n)
+    }
+}
+
+impl<'a> Deref for PooledTagger<'a> {
+    type Target = Tagger;
+
+    fn deref(&self) -> &Tagger {
+        self.tagger.as_ref().unwrap()
+    }
+}
+
+impl<'a> DerefMut for PooledTagger<'a> {
+    fn deref_mut(&mut self) -> &mut Tagger {
+        self.tagger.as_mut().unwrap()
+    }
+}
+
+impl<'a> Drop for PooledTagger<'a> {
+    fn drop(&mut self) {
+        if let (Some(tagger), Some(mut lattice)) = (self.tagger.take(), self.lattice.take()) {
+            lattice.clear();
+            lattice.set_request_type(MECAB_ONE_BEST);
+            lattice.set_theta(self.theta.1);
+            tagger.set_theata(self.theta.0);
+            if let Ok(mut idle) = self.pool.idle.lock() {
+                idle.push((tagger, lattice));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mecab::MECAB_NBEST;
+
+    fn pool() -> TaggerPool {
+        TaggerPool::new(Model::mock(&[("すもも", "名詞,一般,*,*,*,*,すもも,スモモ,スモモ"),
+                                      ("も", "助詞,係助詞,*,*,*,*,も,モ,モ"),
+                                      ("もも", "名詞,一般,*,*,*,*,もも,モモ,モモ")]))
+    }
+
+    fn idle(pool: &TaggerPool) -> usize {
+        pool.idle.lock().unwrap().len()
+    }
+
+    #[test]
+    fn checkout_and_return() {
+        let pool = pool();
+        assert_eq!(idle(&pool), 0);
+        {
+            let first = pool.get();
+            let second = pool.get();
+            assert_eq!(idle(&pool), 0);
+            drop(first);
+            assert_eq!(idle(&pool), 1);
+            drop(second);
+        }
+        assert_eq!(idle(&pool), 2);
+
+        // idle taggers are reused before new ones are created
+        let tagger = pool.get();
+        assert_eq!(idle(&pool), 1);
+        drop(tagger);
+        assert_eq!(idle(&pool), 2);
+    }
+
+    #[test]
+    fn returned_taggers_are_reset() {
+        let pool = pool();
+        let (theta, lattice_theta) = {
+            let mut tagger = pool.get();
+            let theta = (tagger.theta(), tagger.lattice().theta());
+            tagger.set_theata(0.5);
+            tagger.lattice().set_theta(0.25);
+            tagger.lattice().add_request_type(MECAB_NBEST);
+            tagger.lattice_mut().set_sentence("すもも");
+            theta
+        };
+
+        let tagger = pool.get();
+        assert_eq!(idle(&pool), 0);
+        assert_eq!(tagger.theta(), theta);
+        assert_eq!(tagger.lattice().theta(), lattice_theta);
+        assert_eq!(tagger.lattice().request_type(), MECAB_ONE_BEST);
+        assert_eq!(tagger.lattice().sentence(), "");
+    }
+
+    #[test]
+    fn parse_to_tokens() {
+        let pool = pool();
+        let tokens = pool.parse_to_tokens("すもももももも").unwrap();
+        let surfaces: Vec<&str> = tokens.iter().map(|t| t.surface.as_str()).collect();
+        assert_eq!(surfaces, ["すもも", "もも", "もも"]);
+        assert_eq!(tokens[1].span.begin, 9);
+        assert_eq!(tokens[1].reading(), Some("モモ"));
+        assert_eq!(idle(&pool), 1);
+    }
+}
@@ -0,0 +1,595 @@
+use std::sync::Arc;
+
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+
+use graph::{GraphNode, LatticeGraph};
+use mecab::{Model, Tagger, MECAB_MARGINAL_PROB, MECAB_ONE_BEST};
+use nbest::Path;
+use offset::OffsetMap;
+use pool::TaggerPool;
+use token::{split_features, Span, Token};
+
+/// libmecab arguments, either a command line split with shell quoting or a list of arguments.
+#[derive(FromPyObject)]
+enum Args {
+    Line(String),
+    List(Vec<String>),
+}
+
+impl Args {
+    fn into_vec(self) -> PyResult<Vec<String>> {
+        match self {
+            Args::Line(line) => split_args(&line).map_err(PyValueError::new_err),
+            Args::List(args) => Ok(args),
+        }
+    }
+}
+
+// splits a command line like a POSIX shell does, without expansions
+fn split_args(line: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let mut arg: Option<String> = None;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => args.extend(arg.take()),
+            '\'' => {
+                let arg = arg.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => arg.push(c),
+                        None => return Err("unclosed single quote in the arguments".to_owned()),
+                    }
+                }
+            }
+            '"' => {
+                let arg = arg.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => {
+                            match chars.next() {
+                                Some(c @ '"') | Some(c @ '\\') | Some(c @ '$') | Some(c @ '`') => arg.push(c),
+                                Some(c) => {
+                                    arg.push('\\');
+                                    arg.push(c);
+                                }
+                                None => return Err("unclosed double quote in the arguments".to_owned()),
+                            }
+                        }
+                        Some(c) => arg.push(c),
+                        None => return Err("unclosed double quote in the arguments".to_owned()),
+                    }
+                }
+            }
+            '\\' => {
+                if let Some(c) = chars.next() {
+                    arg.get_or_insert_with(String::new).push(c);
+                }
+            }
+            c => arg.get_or_insert_with(String::new).push(c),
+        }
+    }
+    args.extend(arg);
+    Ok(args)
+}
+
+fn load(py: Python<'_>, args: Option<Args>) -> PyResult<Arc<TaggerPool>> {
+    let args = args.map(Args::into_vec).transpose()?.unwrap_or_default();
+    py.allow_threads(move || {
+        let model = Model::with_args(&args);
+        if model.is_available() {
+            Ok(Arc::new(TaggerPool::new(model)))
+        } else {
+            Err(Tagger::with_args(&args).get_last_error())
+        }
+    })
+    .map_err(PyRuntimeError::new_err)
+}
+
+fn check_text(text: &str) -> PyResult<()> {
+    if text.contains('\0') {
+        Err(PyValueError::new_err("text must not contain NUL"))
+    } else {
+        Ok(())
+    }
+}
+
+fn span_tuple(span: Span) -> (usize, usize) {
+    (span.begin, span.end)
+}
+
+/// A loaded dictionary, shared by every tagger created from it.
+#[pyclass(name = "Model", module = "mecab_rs", frozen)]
+pub struct PyModel {
+    pool: Arc<TaggerPool>,
+}
+
+#[pymethods]
+impl PyModel {
+    #[new]
+    #[pyo3(signature = (args = None))]
+    fn new(py: Python<'_>, args: Option<Args>) -> PyResult<PyModel> {
+        load(py, args).map(|pool| PyModel { pool })
+    }
+
+    fn tagger(&self) -> PyTagger {
+        PyTagger { pool: self.pool.clone() }
+    }
+
+    fn dictionary_info(&self) -> Vec<PyDictionaryInfo> {
+        self.pool
+            .model()
+            .dictionary_info()
+            .iter()
+            .map(|dict| {
+                PyDictionaryInfo {
+                    filename: dict.filename.clone(),
+                    charset: dict.charset.clone(),
+                    size: dict.size,
+                    dict_type: dict.dict_type,
+                    lsize: dict.lsize,
+                    rsize: dict.rsize,
+                    version: dict.version,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Tagger backed by a pool, every call checks out its own libmecab tagger so
+/// that one instance can be shared by many Python threads.
+#[pyclass(name = "Tagger", module = "mecab_rs", frozen)]
+pub struct PyTagger {
+    pool: Arc<TaggerPool>,
+}
+
+#[pymethods]
+impl PyTagger {
+    #[new]
+    #[pyo3(signature = (args = None))]
+    fn new(py: Python<'_>, args: Option<Args>) -> PyResult<PyTagger> {
+        load(py, args).map(|pool| PyTagger { pool })
+    }
+
+    fn model(&self) -> PyModel {
+        PyModel { pool: self.pool.clone() }
+    }
+
+    /// Tokens of the best path.
+    fn parse(&self, py: Python<'_>, text: String) -> PyResult<Vec<PyToken>> {
+        check_text(&text)?;
+        let tokens = py.allow_threads(|| self.pool.parse_to_tokens(text))
+            .ok_or_else(|| PyRuntimeError::new_err("parsing failed"))?;
+        Ok(tokens.into_iter().map(|inner| PyToken { inner }).collect())
+    }
+
+    /// Output of libmecab in the format selected by the model arguments.
+    fn parse_to_str(&self, py: Python<'_>, text: String) -> PyResult<String> {
+        check_text(&text)?;
+        py.allow_threads(|| {
+            let mut tagger = self.pool.get();
+            tagger.lattice_mut().set_sentence(text);
+            if tagger.parse(tagger.lattice()) {
+                Ok(tagger.lattice().to_string())
+            } else {
+                Err(tagger.lattice().what())
+            }
+        })
+        .map_err(PyRuntimeError::new_err)
+    }
+
+    /// Up to `n` segmentations in order of increasing cost.
+    #[pyo3(signature = (text, n, marginal = false))]
+    fn nbest(&self, py: Python<'_>, text: String, n: usize, marginal: bool) -> PyResult<Vec<PyPath>> {
+        check_text(&text)?;
+        let paths = py.allow_threads(|| {
+            let mut tagger = self.pool.get();
+            if marginal {
+                tagger.lattice_mut().set_request_type(MECAB_MARGINAL_PROB);
+            }
+            tagger.lattice_mut().set_sentence(text);
+            let paths: Vec<Path> = tagger.nbest(n).collect();
+            if paths.is_empty() && n > 0 {
+                Err(tagger.lattice().what())
+            } else {
+                Ok(paths)
+            }
+        })
+        .map_err(PyRuntimeError::new_err)?;
+        Ok(paths.into_iter().map(|inner| PyPath { inner }).collect())
+    }
+
+    /// Every candidate node of the parsed sentence.
+    #[pyo3(signature = (text, marginal = false))]
+    fn lattice(&self, py: Python<'_>, text: String, marginal: bool) -> PyResult<PyLattice> {
+        check_text(&text)?;
+        let graph = py.allow_threads(|| {
+            let mut tagger = self.pool.get();
+            if marginal {
+                tagger.lattice_mut().set_request_type(MECAB_MARGINAL_PROB);
+            }
+            tagger.lattice_mut().set_sentence(text);
+            let graph = if tagger.parse(tagger.lattice()) {
+                Ok(LatticeGraph::new(tagger.lattice(), self.pool.model()))
+            } else {
+                Err(tagger.lattice().what())
+            };
+            tagger.lattice_mut().set_request_type(MECAB_ONE_BEST);
+            graph
+        })
+        .map_err(PyRuntimeError::new_err)?;
+        let offsets = OffsetMap::new(&graph.sentence);
+        Ok(PyLattice { graph, offsets })
+    }
+}
+
+#[pyclass(name = "DictionaryInfo", module = "mecab_rs", frozen, get_all)]
+pub struct PyDictionaryInfo {
+    filename: String,
+    charset: String,
+    size: u32,
+    dict_type: i32,
+    lsize: u32,
+    rsize: u32,
+    version: u16,
+}
+
+/// Morpheme of a parsed sentence, `span` counts characters so that it slices
+/// the Python string directly.
+#[pyclass(name = "Token", module = "mecab_rs", frozen)]
+pub struct PyToken {
+    inner: Token,
+}
+
+#[pymethods]
+impl PyToken {
+    #[getter]
+    fn surface(&self) -> &str {
+        &self.inner.surface
+    }
+
+    #[getter]
+    fn feature(&self) -> &str {
+        &self.inner.feature
+    }
+
+    #[getter]
+    fn features(&self) -> Vec<&str> {
+        self.inner.features()
+    }
+
+    #[getter]
+    fn span(&self) -> (usize, usize) {
+        span_tuple(self.inner.char_span)
+    }
+
+    #[getter]
+    fn byte_span(&self) -> (usize, usize) {
+        span_tuple(self.inner.span)
+    }
+
+    #[getter]
+    fn utf16_span(&self) -> (usize, usize) {
+        span_tuple(self.inner.utf16_span)
+    }
+
+    #[getter]
+    fn pos(&self) -> &str {
+        self.inner.pos()
+    }
+
+    #[getter]
+    fn reading(&self) -> Option<&str> {
+        self.inner.reading()
+    }
+
+    #[getter]
+    fn pronunciation(&self) -> Option<&str> {
+        self.inner.pronunciation()
+    }
+
+    #[getter]
+    fn lemma(&self) -> &str {
+        self.inner.lemma()
+    }
+
+    #[getter]
+    fn conjugation_type(&self) -> Option<&str> {
+        self.inner.conjugation_type()
+    }
+
+    #[getter]
+    fn conjugation_form(&self) -> Option<&str> {
+        self.inner.conjugation_form()
+    }
+
+    #[getter]
+    fn stat(&self) -> u8 {
+        self.inner.stat
+    }
+
+    #[getter]
+    fn posid(&self) -> u16 {
+        self.inner.posid
+    }
+
+    #[getter]
+    fn lcattr(&self) -> u16 {
+        self.inner.lcattr
+    }
+
+    #[getter]
+    fn rcattr(&self) -> u16 {
+        self.inner.rcattr
+    }
+
+    #[getter]
+    fn wcost(&self) -> i16 {
+        self.inner.wcost
+    }
+
+    #[getter]
+    fn cost(&self) -> i64 {
+        self.inner.cost
+    }
+
+    #[getter]
+    fn prob(&self) -> f32 {
+        self.inner.prob
+    }
+
+    /// True if the feature starts with `pattern`, `*` matches any field.
+    fn matches_pos(&self, pattern: Vec<String>) -> bool {
+        self.inner.matches_pos(&pattern)
+    }
+
+    fn __str__(&self) -> &str {
+        &self.inner.surface
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Token({:?}, {:?}, span=({}, {}))",
+                self.inner.surface,
+                self.inner.feature,
+                self.inner.char_span.begin,
+                self.inner.char_span.end)
+    }
+}
+
+/// One segmentation returned by `Tagger.nbest`.
+#[pyclass(name = "Path", module = "mecab_rs", frozen)]
+pub struct PyPath {
+    inner: Path,
+}
+
+#[pymethods]
+impl PyPath {
+    #[getter]
+    fn tokens(&self) -> Vec<PyToken> {
+        self.inner.tokens.iter().map(|t| PyToken { inner: t.clone() }).collect()
+    }
+
+    #[getter]
+    fn cost(&self) -> i64 {
+        self.inner.cost
+    }
+
+    /// Probability of the path, `None` unless `marginal` was requested.
+    #[getter]
+    fn prob(&self) -> Option<f64> {
+        self.inner.prob
+    }
+
+    fn __len__(&self) -> usize {
+        self.inner.tokens.len()
+    }
+
+    fn __repr__(&self) -> String {
+        let surfaces: Vec<&str> = self.inner.tokens.iter().map(|t| t.surface.as_str()).collect();
+        format!("Path({:?}, cost={})", surfaces, self.inner.cost)
+    }
+}
+
+/// Candidate node of a `Lattice`.
+#[pyclass(name = "Node", module = "mecab_rs", frozen)]
+pub struct PyNode {
+    inner: GraphNode,
+    char_span: Span,
+}
+
+impl PyNode {
+    fn new(node: &GraphNode, offsets: &OffsetMap) -> PyNode {
+        PyNode {
+            inner: node.clone(),
+            char_span: offsets.byte_span_to_char(node.span).unwrap_or(node.span),
+        }
+    }
+}
+
+#[pymethods]
+impl PyNode {
+    #[getter]
+    fn id(&self) -> usize {
+        self.inner.id
+    }
+
+    #[getter]
+    fn surface(&self) -> &str {
+        &self.inner.surface
+    }
+
+    #[getter]
+    fn feature(&self) -> &str {
+        &self.inner.feature
+    }
+
+    #[getter]
+    fn features(&self) -> Vec<&str> {
+        split_features(&self.inner.feature)
+    }
+
+    #[getter]
+    fn span(&self) -> (usize, usize) {
+        span_tuple(self.char_span)
+    }
+
+    #[getter]
+    fn byte_span(&self) -> (usize, usize) {
+        span_tuple(self.inner.span)
+    }
+
+    #[getter]
+    fn stat(&self) -> u8 {
+        self.inner.stat
+    }
+
+    #[getter]
+    fn posid(&self) -> u16 {
+        self.inner.posid
+    }
+
+    #[getter]
+    fn lcattr(&self) -> u16 {
+        self.inner.lcattr
+    }
+
+    #[getter]
+    fn rcattr(&self) -> u16 {
+        self.inner.rcattr
+    }
+
+    #[getter]
+    fn wcost(&self) -> i16 {
+        self.inner.wcost
+    }
+
+    #[getter]
+    fn cost(&self) -> i64 {
+        self.inner.cost
+    }
+
+    #[getter]
+    fn is_best(&self) -> bool {
+        self.inner.is_best
+    }
+
+    #[getter]
+    fn prob(&self) -> Option<f32> {
+        self.inner.prob
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Node({}, {:?}, {:?})", self.inner.id, self.inner.surface, self.inner.feature)
+    }
+}
+
+/// Snapshot of every candidate node and connection of a parsed sentence.
+///
+/// Edges are `(from, to, cost)` tuples of node ids.
+#[pyclass(name = "Lattice", module = "mecab_rs", frozen)]
+pub struct PyLattice {
+    graph: LatticeGraph,
+    offsets: OffsetMap,
+}
+
+impl PyLattice {
+    fn nodes_of<'a, I: IntoIterator<Item = &'a GraphNode>>(&self, nodes: I) -> Vec<PyNode> {
+        nodes.into_iter().map(|node| PyNode::new(node, &self.offsets)).collect()
+    }
+}
+
+#[pymethods]
+impl PyLattice {
+    #[getter]
+    fn sentence(&self) -> &str {
+        &self.graph.sentence
+    }
+
+    #[getter]
+    fn nodes(&self) -> Vec<PyNode> {
+        self.nodes_of(&self.graph.nodes)
+    }
+
+    #[getter]
+    fn edges(&self) -> Vec<(usize, usize, i32)> {
+        self.graph.edges.iter().map(|e| (e.from, e.to, e.cost)).collect()
+    }
+
+    /// Node ids of the best path from BOS to EOS.
+    #[getter]
+    fn best_path(&self) -> Vec<usize> {
+        self.graph.best_path.clone()
+    }
+
+    /// Log of the partition function, `None` unless `marginal` was requested.
+    #[getter]
+    fn z(&self) -> Option<f64> {
+        self.graph.z
+    }
+
+    fn node(&self, id: usize) -> Option<PyNode> {
+        self.graph.node(id).map(|node| PyNode::new(node, &self.offsets))
+    }
+
+    /// Nodes starting at character offset `pos`.
+    fn nodes_at(&self, pos: usize) -> Vec<PyNode> {
+        match self.offsets.char_to_byte(pos) {
+            Some(pos) => self.nodes_of(self.graph.nodes_at(pos)),
+            None => Vec::new(),
+        }
+    }
+
+    fn outgoing(&self, id: usize) -> Vec<(usize, usize, i32)> {
+        self.graph.outgoing(id).into_iter().map(|e| (e.from, e.to, e.cost)).collect()
+    }
+
+    fn incoming(&self, id: usize) -> Vec<(usize, usize, i32)> {
+        self.graph.incoming(id).into_iter().map(|e| (e.from, e.to, e.cost)).collect()
+    }
+
+    fn to_dot(&self) -> String {
+        self.graph.to_dot()
+    }
+
+    fn __len__(&self) -> usize {
+        self.graph.nodes.len()
+    }
+}
+
+#[pymodule]
+fn mecab_rs(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyModel>()?;
+    m.add_class::<PyTagger>()?;
+    m.add_class::<PyDictionaryInfo>()?;
+    m.add_class::<PyToken>()?;
+    m.add_class::<PyPath>()?;
+    m.add_class::<PyNode>()?;
+    m.add_class::<PyLattice>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_args_quoting() {
+        assert_eq!(split_args("-d /usr/lib/mecab/dic  -Owakati").unwrap(),
+                   vec!["-d", "/usr/lib/mecab/dic", "-Owakati"]);
+        assert_eq!(split_args(r#"-d "/opt/my dic" -u '/tmp/user dic.dic'"#).unwrap(),
+                   vec!["-d", "/opt/my dic", "-u", "/tmp/user dic.dic"]);
+        assert_eq!(split_args(r#"-d /opt/my\ dic "a\"b" '' "#).unwrap(),
+                   vec!["-d", "/opt/my dic", "a\"b", ""]);
+        assert!(split_args("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn split_args_unclosed_quote() {
+        assert!(split_args("-d '/opt/dic").is_err());
+        assert!(split_args("-d \"/opt/dic").is_err());
+    }
+}
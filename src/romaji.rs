@@ -0,0 +1,459 @@
+use kana::katakana_to_hiragana;
+use token::Token;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RomajiSystem {
+    Hepburn,
+    Kunrei,
+    NihonShiki,
+}
+
+/// How the prolonged sound mark `ー` is written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LongVowel {
+    /// `ā` in Hepburn, `â` in Kunrei and Nihon-shiki.
+    Macron,
+    /// `aa`
+    Double,
+}
+
+/// Converts token readings or pronunciations to romaji.
+///
+/// Pronunciations spell long vowels with `ー` (トーキョー) and readings with a
+/// vowel kana (トウキョウ). With `LongVowel::Macron` both give `tōkyō`, with
+/// `LongVowel::Double` they give `tookyoo` and `toukyou`. A small `っ`
+/// without a consonant to double, as in `あっ`, is dropped.
+#[derive(Clone, Debug)]
+pub struct Romanizer {
+    system: RomajiSystem,
+    long_vowel: LongVowel,
+    pronunciation: bool,
+}
+
+impl Romanizer {
+    pub fn new(system: RomajiSystem) -> Romanizer {
+        Romanizer {
+            system,
+            long_vowel: LongVowel::Macron,
+            pronunciation: true,
+        }
+    }
+
+    pub fn long_vowel(mut self, long_vowel: LongVowel) -> Romanizer {
+        self.long_vowel = long_vowel;
+        self
+    }
+
+    /// Uses the pronunciation field instead of the reading field, enabled by default.
+    pub fn pronunciation(mut self, enable: bool) -> Romanizer {
+        self.pronunciation = enable;
+        self
+    }
+
+    /// Romanizes hiragana and katakana, other chars are kept.
+    pub fn kana(&self, text: &str) -> String {
+        self.romanize(text, false)
+    }
+
+    // `final_u` keeps a final `う` apart from the vowel before it, as in the verb 思う (omou)
+    fn romanize(&self, text: &str, final_u: bool) -> String {
+        let chars: Vec<char> = katakana_to_hiragana(text).chars().collect();
+        let mut out = String::new();
+        let mut sokuon = false;
+        let mut i = 0;
+
+        while i < chars.len() {
+            let c = chars[i];
+            match c {
+                'っ' => {
+                    if sokuon {
+                        out.push_str(self.pick(["xtsu", "xtu", "xtu"]));
+                    }
+                    sokuon = true;
+                    i += 1;
+                    continue;
+                }
+                'ー' => {
+                    self.lengthen(&mut out);
+                    i += 1;
+                    continue;
+                }
+                _ => {}
+            }
+
+            let last = i + 1 == chars.len();
+            if self.long_vowel == LongVowel::Macron && !sokuon && !(final_u && last && c == 'う') &&
+               chars.get(i + 1).and_then(|&n| digraph(c, n)).is_none() && lengthens(&out, c) {
+                self.lengthen(&mut out);
+                i += 1;
+                continue;
+            }
+
+            let (romaji, len) = match (chars.get(i + 1).and_then(|&n| digraph(c, n)), syllable(c)) {
+                (Some(r), _) => (self.pick(r).to_owned(), 2),
+                (None, Some(r)) => (self.pick(r).to_owned(), 1),
+                (None, None) => (punctuation(c).unwrap_or(c).to_string(), 1),
+            };
+
+            if sokuon {
+                // double the consonant, Hepburn writes `tch` instead of `cch`
+                match romaji.chars().next() {
+                    Some(first) if is_consonant(first) => {
+                        if self.system == RomajiSystem::Hepburn && romaji.starts_with("ch") {
+                            out.push('t');
+                        } else {
+                            out.push(first);
+                        }
+                    }
+                    _ => {}
+                }
+                sokuon = false;
+            }
+            if out.ends_with('n') && chars[..i].last() == Some(&'ん') &&
+               romaji.starts_with(|c| "aiueoy".contains(c)) {
+                out.push('\'');
+            }
+            out.push_str(&romaji);
+            i += len;
+        }
+
+        out
+    }
+
+    /// Romanizes a token, particles `は`, `へ` and `を` are written as they are pronounced.
+    pub fn token(&self, token: &Token) -> String {
+        let kana = if self.pronunciation {
+            token.pronunciation().or_else(|| token.reading())
+        } else {
+            token.reading()
+        };
+        let kana = match kana {
+            Some(kana) => kana.to_owned(),
+            None => token.surface.clone(),
+        };
+
+        if token.pos() == "助詞" {
+            match &katakana_to_hiragana(&kana)[..] {
+                "は" => return "wa".to_owned(),
+                "へ" => return "e".to_owned(),
+                "を" => return self.pick(["o", "o", "wo"]).to_owned(),
+                _ => {}
+            }
+        }
+
+        self.romanize(&kana, token.pos() == "動詞")
+    }
+
+    /// Romanizes a sentence with spaces between words and punctuation attached to the preceding word.
+    pub fn sentence(&self, tokens: &[Token]) -> String {
+        let mut out = String::new();
+        for token in tokens {
+            let romaji = self.token(token);
+            let attach = token.surface.chars().all(|c| punctuation(c).is_some());
+            if !out.is_empty() && !attach {
+                out.push(' ');
+            }
+            out.push_str(&romaji);
+        }
+        out
+    }
+
+    fn pick(&self, romaji: [&'static str; 3]) -> &'static str {
+        match self.system {
+            RomajiSystem::Hepburn => romaji[0],
+            RomajiSystem::Kunrei => romaji[1],
+            RomajiSystem::NihonShiki => romaji[2],
+        }
+    }
+
+    fn lengthen(&self, out: &mut String) {
+        let last = match out.chars().last() {
+            Some(c) => c,
+            None => return,
+        };
+        let marked = match (self.long_vowel, self.system) {
+            (LongVowel::Double, _) => None,
+            (LongVowel::Macron, RomajiSystem::Hepburn) => {
+                "aiueo".find(last).map(|i| ['ā', 'ī', 'ū', 'ē', 'ō'][i])
+            }
+            (LongVowel::Macron, _) => "aiueo".find(last).map(|i| ['â', 'î', 'û', 'ê', 'ô'][i]),
+        };
+        match marked {
+            Some(m) => {
+                out.pop();
+                out.push(m);
+            }
+            None if "aiueo".contains(last) => out.push(last),
+            None => {}
+        }
+    }
+}
+
+// true if the vowel kana `c` prolongs the vowel romanized last, as in こう or ねえ
+fn lengthens(out: &str, c: char) -> bool {
+    matches!((out.chars().last(), c),
+             (Some('o'), 'う') | (Some('u'), 'う') | (Some('o'), 'お') | (Some('a'), 'あ') | (Some('e'), 'え'))
+}
+
+fn is_consonant(c: char) -> bool {
+    c.is_ascii_lowercase() && !"aiueon".contains(c)
+}
+
+fn punctuation(c: char) -> Option<char> {
+    match c {
+        '。' | '．' => Some('.'),
+        '、' | '，' => Some(','),
+        '！' => Some('!'),
+        '？' => Some('?'),
+        '「' | '」' | '『' | '』' => Some('"'),
+        '・' => Some('-'),
+        '.' | ',' | '!' | '?' => Some(c),
+        _ => None,
+    }
+}
+
+// romaji in Hepburn, Kunrei and Nihon-shiki
+fn syllable(c: char) -> Option<[&'static str; 3]> {
+    let r = match c {
+        'あ' | 'ぁ' => ["a", "a", "a"],
+        'い' | 'ぃ' => ["i", "i", "i"],
+        'う' | 'ぅ' => ["u", "u", "u"],
+        'え' | 'ぇ' => ["e", "e", "e"],
+        'お' | 'ぉ' => ["o", "o", "o"],
+        'か' | 'ゕ' => ["ka", "ka", "ka"],
+        'き' => ["ki", "ki", "ki"],
+        'く' => ["ku", "ku", "ku"],
+        'け' | 'ゖ' => ["ke", "ke", "ke"],
+        'こ' => ["ko", "ko", "ko"],
+        'さ' => ["sa", "sa", "sa"],
+        'し' => ["shi", "si", "si"],
+        'す' => ["su", "su", "su"],
+        'せ' => ["se", "se", "se"],
+        'そ' => ["so", "so", "so"],
+        'た' => ["ta", "ta", "ta"],
+        'ち' => ["chi", "ti", "ti"],
+        'つ' => ["tsu", "tu", "tu"],
+        'て' => ["te", "te", "te"],
+        'と' => ["to", "to", "to"],
+        'な' => ["na", "na", "na"],
+        'に' => ["ni", "ni", "ni"],
+        'ぬ' => ["nu", "nu", "nu"],
+        'ね' => ["ne", "ne", "ne"],
+        'の' => ["no", "no", "no"],
+        'は' => ["ha", "ha", "ha"],
+        'ひ' => ["hi", "hi", "hi"],
+        'ふ' => ["fu", "hu", "hu"],
+        'へ' => ["he", "he", "he"],
+        'ほ' => ["ho", "ho", "ho"],
+        'ま' => ["ma", "ma", "ma"],
+        'み' => ["mi", "mi", "mi"],
+        'む' => ["mu", "mu", "mu"],
+        'め' => ["me", "me", "me"],
+        'も' => ["mo", "mo", "mo"],
+        'や' | 'ゃ' => ["ya", "ya", "ya"],
+        'ゆ' | 'ゅ' => ["yu", "yu", "yu"],
+        'よ' | 'ょ' => ["yo", "yo", "yo"],
+        'ら' => ["ra", "ra", "ra"],
+        'り' => ["ri", "ri", "ri"],
+        'る' => ["ru", "ru", "ru"],
+        'れ' => ["re", "re", "re"],
+        'ろ' => ["ro", "ro", "ro"],
+        'わ' | 'ゎ' => ["wa", "wa", "wa"],
+        'ゐ' => ["i", "i", "wi"],
+        'ゑ' => ["e", "e", "we"],
+        'を' => ["o", "o", "wo"],
+        'ん' => ["n", "n", "n"],
+        'が' => ["ga", "ga", "ga"],
+        'ぎ' => ["gi", "gi", "gi"],
+        'ぐ' => ["gu", "gu", "gu"],
+        'げ' => ["ge", "ge", "ge"],
+        'ご' => ["go", "go", "go"],
+        'ざ' => ["za", "za", "za"],
+        'じ' => ["ji", "zi", "zi"],
+        'ず' => ["zu", "zu", "zu"],
+        'ぜ' => ["ze", "ze", "ze"],
+        'ぞ' => ["zo", "zo", "zo"],
+        'だ' => ["da", "da", "da"],
+        'ぢ' => ["ji", "zi", "di"],
+        'づ' => ["zu", "zu", "du"],
+        'で' => ["de", "de", "de"],
+        'ど' => ["do", "do", "do"],
+        'ば' => ["ba", "ba", "ba"],
+        'び' => ["bi", "bi", "bi"],
+        'ぶ' => ["bu", "bu", "bu"],
+        'べ' => ["be", "be", "be"],
+        'ぼ' => ["bo", "bo", "bo"],
+        'ぱ' => ["pa", "pa", "pa"],
+        'ぴ' => ["pi", "pi", "pi"],
+        'ぷ' => ["pu", "pu", "pu"],
+        'ぺ' => ["pe", "pe", "pe"],
+        'ぽ' => ["po", "po", "po"],
+        'ゔ' => ["vu", "vu", "vu"],
+        _ => return None,
+    };
+    Some(r)
+}
+
+// contracted sounds written with a small kana
+fn digraph(c: char, small: char) -> Option<[&'static str; 3]> {
+    let (hepburn, kunrei, nihon) = match (c, small) {
+        ('き', 'ゃ') => ("kya", "kya", "kya"),
+        ('き', 'ゅ') => ("kyu", "kyu", "kyu"),
+        ('き', 'ょ') => ("kyo", "kyo", "kyo"),
+        ('し', 'ゃ') => ("sha", "sya", "sya"),
+        ('し', 'ゅ') => ("shu", "syu", "syu"),
+        ('し', 'ょ') => ("sho", "syo", "syo"),
+        ('し', 'ぇ') => ("she", "sye", "sye"),
+        ('ち', 'ゃ') => ("cha", "tya", "tya"),
+        ('ち', 'ゅ') => ("chu", "tyu", "tyu"),
+        ('ち', 'ょ') => ("cho", "tyo", "tyo"),
+        ('ち', 'ぇ') => ("che", "tye", "tye"),
+        ('に', 'ゃ') => ("nya", "nya", "nya"),
+        ('に', 'ゅ') => ("nyu", "nyu", "nyu"),
+        ('に', 'ょ') => ("nyo", "nyo", "nyo"),
+        ('ひ', 'ゃ') => ("hya", "hya", "hya"),
+        ('ひ', 'ゅ') => ("hyu", "hyu", "hyu"),
+        ('ひ', 'ょ') => ("hyo", "hyo", "hyo"),
+        ('み', 'ゃ') => ("mya", "mya", "mya"),
+        ('み', 'ゅ') => ("myu", "myu", "myu"),
+        ('み', 'ょ') => ("myo", "myo", "myo"),
+        ('り', 'ゃ') => ("rya", "rya", "rya"),
+        ('り', 'ゅ') => ("ryu", "ryu", "ryu"),
+        ('り', 'ょ') => ("ryo", "ryo", "ryo"),
+        ('ぎ', 'ゃ') => ("gya", "gya", "gya"),
+        ('ぎ', 'ゅ') => ("gyu", "gyu", "gyu"),
+        ('ぎ', 'ょ') => ("gyo", "gyo", "gyo"),
+        ('じ', 'ゃ') => ("ja", "zya", "zya"),
+        ('じ', 'ゅ') => ("ju", "zyu", "zyu"),
+        ('じ', 'ょ') => ("jo", "zyo", "zyo"),
+        ('じ', 'ぇ') => ("je", "zye", "zye"),
+        ('ぢ', 'ゃ') => ("ja", "zya", "dya"),
+        ('ぢ', 'ゅ') => ("ju", "zyu", "dyu"),
+        ('ぢ', 'ょ') => ("jo", "zyo", "dyo"),
+        ('び', 'ゃ') => ("bya", "bya", "bya"),
+        ('び', 'ゅ') => ("byu", "byu", "byu"),
+        ('び', 'ょ') => ("byo", "byo", "byo"),
+        ('ぴ', 'ゃ') => ("pya", "pya", "pya"),
+        ('ぴ', 'ゅ') => ("pyu", "pyu", "pyu"),
+        ('ぴ', 'ょ') => ("pyo", "pyo", "pyo"),
+        ('く', 'ゎ') => ("kwa", "kwa", "kwa"),
+        ('ぐ', 'ゎ') => ("gwa", "gwa", "gwa"),
+        ('ふ', 'ぁ') => ("fa", "fa", "fa"),
+        ('ふ', 'ぃ') => ("fi", "fi", "fi"),
+        ('ふ', 'ぇ') => ("fe", "fe", "fe"),
+        ('ふ', 'ぉ') => ("fo", "fo", "fo"),
+        ('ふ', 'ゅ') => ("fyu", "fyu", "fyu"),
+        ('て', 'ぃ') => ("ti", "ti", "ti"),
+        ('て', 'ゅ') => ("tyu", "tyu", "tyu"),
+        ('で', 'ぃ') => ("di", "di", "di"),
+        ('で', 'ゅ') => ("dyu", "dyu", "dyu"),
+        ('と', 'ぅ') => ("tu", "tu", "tu"),
+        ('ど', 'ぅ') => ("du", "du", "du"),
+        ('つ', 'ぁ') => ("tsa", "tsa", "tsa"),
+        ('つ', 'ぃ') => ("tsi", "tsi", "tsi"),
+        ('つ', 'ぇ') => ("tse", "tse", "tse"),
+        ('つ', 'ぉ') => ("tso", "tso", "tso"),
+        ('う', 'ぃ') => ("wi", "wi", "wi"),
+        ('う', 'ぇ') => ("we", "we", "we"),
+        ('う', 'ぉ') => ("wo", "wo", "wo"),
+        ('い', 'ぇ') => ("ye", "ye", "ye"),
+        ('ゔ', 'ぁ') => ("va", "va", "va"),
+        ('ゔ', 'ぃ') => ("vi", "vi", "vi"),
+        ('ゔ', 'ぇ') => ("ve", "ve", "ve"),
+        ('ゔ', 'ぉ') => ("vo", "vo", "vo"),
+        _ => return None,
+    };
+    Some([hepburn, kunrei, nihon])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mecab::MECAB_NOR_NODE;
+    use token::Span;
+
+    fn token(surface: &str, feature: &str) -> Token {
+        Token {
+            surface: surface.to_owned(),
+            feature: feature.to_owned(),
+            span: Span::default(),
+            char_span: Span::default(),
+            utf16_span: Span::default(),
+            stat: MECAB_NOR_NODE as u8,
+            posid: 0,
+            lcattr: 0,
+            rcattr: 0,
+            wcost: 0,
+            cost: 0,
+            prob: 0.0,
+        }
+    }
+
+    #[test]
+    fn systems() {
+        let hepburn = Romanizer::new(RomajiSystem::Hepburn);
+        let kunrei = Romanizer::new(RomajiSystem::Kunrei);
+        assert_eq!(hepburn.kana("しんぶん"), "shinbun");
+        assert_eq!(kunrei.kana("ちゃしつ"), "tyasitu");
+        assert_eq!(hepburn.kana("きんえん"), "kin'en");
+        assert_eq!(hepburn.kana("まっちゃ"), "matcha");
+    }
+
+    #[test]
+    fn long_vowels_of_readings() {
+        let macron = Romanizer::new(RomajiSystem::Hepburn);
+        assert_eq!(macron.kana("ガッコウ"), "gakkō");
+        assert_eq!(macron.kana("トウキョウ"), "tōkyō");
+        assert_eq!(macron.kana("トーキョー"), "tōkyō");
+        assert_eq!(macron.kana("クウキ"), "kūki");
+        assert_eq!(macron.kana("オネエサン"), "onēsan");
+        // ei and ii are written as they are
+        assert_eq!(macron.kana("センセイ"), "sensei");
+        assert_eq!(macron.kana("オイシイ"), "oishii");
+        assert_eq!(Romanizer::new(RomajiSystem::Kunrei).kana("ガッコウ"), "gakkô");
+
+        let double = Romanizer::new(RomajiSystem::Hepburn).long_vowel(LongVowel::Double);
+        assert_eq!(double.kana("ガッコウ"), "gakkou");
+        assert_eq!(double.kana("トーキョー"), "tookyoo");
+    }
+
+    #[test]
+    fn verbs_keep_their_final_u() {
+        let romanizer = Romanizer::new(RomajiSystem::Hepburn).pronunciation(false);
+        assert_eq!(romanizer.token(&token("思う", "動詞,自立,*,*,五段・ワ行促音便,基本形,思う,オモウ,オモウ")),
+                   "omou");
+        assert_eq!(romanizer.token(&token("学校", "名詞,一般,*,*,*,*,学校,ガッコウ,ガッコー")), "gakkō");
+    }
+
+    #[test]
+    fn trailing_sokuon() {
+        let romanizer = Romanizer::new(RomajiSystem::Hepburn);
+        assert_eq!(romanizer.kana("あっ"), "a");
+        assert_eq!(romanizer.kana("あっ。"), "a.");
+        assert_eq!(romanizer.kana("っっか"), "xtsukka");
+    }
+
+    #[test]
+    fn particles() {
+        let haha = token("母", "名詞,一般,*,*,*,*,母,ハハ,ハハ");
+        let wa = token("は", "助詞,係助詞,*,*,*,*,は,ハ,ワ");
+        let heya = token("部屋", "名詞,一般,*,*,*,*,部屋,ヘヤ,ヘヤ");
+        let e = token("へ", "助詞,格助詞,一般,*,*,*,へ,ヘ,エ");
+        let otaku = token("ヲタク", "名詞,一般,*,*,*,*,ヲタク,ヲタク,ヲタク");
+        let o = token("を", "助詞,格助詞,一般,*,*,*,を,ヲ,ヲ");
+        let tokens = [haha, wa, heya, e, otaku, o];
+
+        for &pronunciation in &[true, false] {
+            for &(system, expected) in &[(RomajiSystem::Hepburn, "haha wa heya e otaku o"),
+                                         (RomajiSystem::Kunrei, "haha wa heya e otaku o"),
+                                         (RomajiSystem::NihonShiki, "haha wa heya e wotaku wo")] {
+                let romanizer = Romanizer::new(system).pronunciation(pronunciation);
+                assert_eq!(romanizer.sentence(&tokens), expected, "{:?}", system);
+            }
+        }
+
+        // は as a content word is not a particle
+        let ha = token("葉", "名詞,一般,*,*,*,*,葉,ハ,ハ");
+        assert_eq!(Romanizer::new(RomajiSystem::Hepburn).token(&ha), "ha");
+    }
+}
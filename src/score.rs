@@ -0,0 +1,209 @@
+use std::error::Error;
+use std::fmt;
+
+use constraints::{ConstraintError, Constraints};
+use mecab::{Lattice, Model, Tagger, MECAB_UNK_NODE};
+use nbest::Path;
+use token::Token;
+
+#[cfg(feature = "json")]
+use serde::{Deserialize, Serialize};
+
+/// One token of a proposed segmentation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "json", derive(Serialize, Deserialize))]
+pub struct Segment {
+    pub surface: String,
+    /// Feature pattern the token has to match, e.g. `名詞,固有名詞,*`.
+    pub pos: Option<String>,
+}
+
+impl Segment {
+    pub fn new<T: Into<String>>(surface: T) -> Segment {
+        Segment {
+            surface: surface.into(),
+            pos: None,
+        }
+    }
+
+    pub fn pos<T: Into<String>>(mut self, pos: T) -> Segment {
+        self.pos = Some(pos.into());
+        self
+    }
+}
+
+impl<'a> From<&'a str> for Segment {
+    fn from(surface: &'a str) -> Segment {
+        Segment::new(surface)
+    }
+}
+
+#[derive(Debug)]
+pub enum ScoreError {
+    /// The segmentation has no tokens or contains a NUL byte.
+    InvalidSegmentation,
+    Constraint(ConstraintError),
+    /// libmecab failed to parse the sentence.
+    Parse(String),
+}
+
+impl fmt::Display for ScoreError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScoreError::InvalidSegmentation => write!(f, "the segmentation is empty or contains NUL"),
+            ScoreError::Constraint(e) => write!(f, "{}", e),
+            ScoreError::Parse(e) => write!(f, "parsing failed: {}", e),
+        }
+    }
+}
+
+impl Error for ScoreError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ScoreError::Constraint(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ConstraintError> for ScoreError {
+    fn from(e: ConstraintError) -> ScoreError {
+        ScoreError::Constraint(e)
+    }
+}
+
+/// Stretch of the sentence where the proposed and the best path disagree.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "json", derive(Serialize, Deserialize))]
+pub struct TokenDifference {
+    pub proposed: Vec<Token>,
+    pub best: Vec<Token>,
+    /// How much more the proposed tokens cost than the best ones, connections included.
+    pub cost_delta: i64,
+}
+
+/// Cost of a proposed segmentation compared with the best path.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "json", derive(Serialize, Deserialize))]
+pub struct SegmentationScore {
+    /// Cheapest path that follows the segmentation.
+    pub proposed: Path,
+    pub best: Path,
+    /// `proposed.cost - best.cost`, zero if the segmentation is the best path.
+    pub cost_delta: i64,
+    pub differences: Vec<TokenDifference>,
+}
+
+impl SegmentationScore {
+    /// Parses the concatenated surfaces once freely and once constrained to
+    /// `segments`. The lattice is cleared afterwards.
+    pub fn new<S: AsRef<[Segment]>>(tagger: &Tagger,
+                                    lattice: &mut Lattice,
+                                    segments: S)
+                                    -> Result<SegmentationScore, ScoreError> {
+        let segments = segments.as_ref();
+        let sentence: String = segments.iter().map(|s| s.surface.as_str()).collect();
+        if segments.is_empty() || sentence.contains('\0') {
+            return Err(ScoreError::InvalidSegmentation);
+        }
+
+        let mut constraints = Constraints::new();
+        let mut begin = 0;
+        for segment in segments {
+            let range = begin..begin + segment.surface.len();
+            match segment.pos {
+                Some(ref pos) => constraints.feature_bytes(range, pos.as_str()),
+                None => constraints.token_bytes(range),
+            };
+            begin += segment.surface.len();
+        }
+
+        let best = parse(tagger, lattice, &sentence, None);
+        let proposed = best.and_then(|best| {
+            parse(tagger, lattice, &sentence, Some(&constraints)).map(|proposed| (proposed, best))
+        });
+        lattice.clear();
+        let (proposed, best) = proposed?;
+
+        Ok(SegmentationScore {
+            cost_delta: proposed.cost - best.cost,
+            differences: differences(&proposed.tokens, &best.tokens),
+            proposed,
+            best,
+        })
+    }
+
+    /// True if every proposed token is a dictionary word.
+    pub fn is_in_dictionary(&self) -> bool {
+        self.proposed.tokens.iter().all(|t| t.stat as i32 != MECAB_UNK_NODE)
+    }
+}
+
+fn parse(tagger: &Tagger,
+         lattice: &mut Lattice,
+         sentence: &str,
+         constraints: Option<&Constraints>)
+         -> Result<Path, ScoreError> {
+    lattice.clear();
+    lattice.set_sentence(sentence);
+    if let Some(constraints) = constraints {
+        constraints.apply(lattice)?;
+    }
+    if tagger.parse(lattice) {
+        Ok(Path::from_lattice(lattice))
+    } else {
+        Err(ScoreError::Parse(lattice.what()))
+    }
+}
+
+// splits both paths at their shared boundaries and keeps the stretches that differ,
+// token costs are cumulative so the delta of a stretch is the change of the delta across it
+fn differences(proposed: &[Token], best: &[Token]) -> Vec<TokenDifference> {
+    let mut differences = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    let mut delta_before = 0;
+
+    while i < proposed.len() && j < best.len() {
+        let (start_i, start_j) = (i, j);
+        i += 1;
+        j += 1;
+        while proposed[i - 1].span.end != best[j - 1].span.end {
+            let behind = proposed[i - 1].span.end < best[j - 1].span.end;
+            if behind && i < proposed.len() {
+                i += 1;
+            } else if !behind && j < best.len() {
+                j += 1;
+            } else {
+                // the paths never meet again, e.g. around skipped whitespace
+                i = proposed.len();
+                j = best.len();
+                break;
+            }
+        }
+
+        let delta = proposed[i - 1].cost - best[j - 1].cost;
+        let same = i - start_i == j - start_j &&
+                   proposed[start_i..i].iter().zip(&best[start_j..j]).all(|(p, b)| {
+            p.span == b.span && p.feature == b.feature
+        });
+        if !same {
+            differences.push(TokenDifference {
+                proposed: proposed[start_i..i].to_vec(),
+                best: best[start_j..j].to_vec(),
+                cost_delta: delta - delta_before,
+            });
+        }
+        delta_before = delta;
+    }
+
+    differences
+}
+
+impl Model {
+    /// Scores a proposed segmentation, see `SegmentationScore::new`.
+    pub fn score_segmentation<S: AsRef<[Segment]>>(&self, segments: S) -> Result<SegmentationScore, ScoreError> {
+        let tagger = self.create_tagger();
+        let mut lattice = self.create_lattice();
+        SegmentationScore::new(&tagger, &mut lattice, segments)
+    }
+}
@@ -0,0 +1,237 @@
+use offset::OffsetMap;
+use token::{Span, Token};
+
+const OPENING: &[char] = &['「', '『', '（', '(', '【', '〔', '［', '[', '〈', '《', '“'];
+const CLOSING: &[char] = &['」', '』', '）', ')', '】', '〕', '］', ']', '〉', '》', '”'];
+const ELLIPSES: &[char] = &['…', '‥'];
+/// Chars after an opening bracket within which its closing bracket has to appear.
+const MAX_BRACKET_SPAN: usize = 500;
+
+/// A sentence of a document.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sentence<'a> {
+    pub text: &'a str,
+    /// Byte span within the document.
+    pub span: Span,
+    pub char_span: Span,
+    pub utf16_span: Span,
+}
+
+impl<'a> Sentence<'a> {
+    /// Moves the spans of a token parsed from this sentence into the document.
+    pub fn map_token(&self, mut token: Token) -> Token {
+        token.span = shift(token.span, self.span.begin);
+        token.char_span = shift(token.char_span, self.char_span.begin);
+        token.utf16_span = shift(token.utf16_span, self.utf16_span.begin);
+        token
+    }
+}
+
+fn shift(span: Span, offset: usize) -> Span {
+    Span::new(span.begin + offset, span.end + offset)
+}
+
+/// Splits Japanese text into sentences.
+///
+/// Sentences end after a terminator and the closing brackets following it.
+/// Terminators and line breaks inside brackets or quotes do not split, a
+/// blank line always does. Brackets that are not closed before the next blank
+/// line or within 500 chars are ignored.
+#[derive(Clone, Debug)]
+pub struct SentenceSplitter {
+    terminators: Vec<char>,
+    abbreviations: Vec<String>,
+    split_lines: bool,
+}
+
+impl Default for SentenceSplitter {
+    fn default() -> SentenceSplitter {
+        SentenceSplitter {
+            terminators: "。！？!?".chars().collect(),
+            abbreviations: Vec::new(),
+            split_lines: true,
+        }
+    }
+}
+
+impl SentenceSplitter {
+    pub fn new() -> SentenceSplitter {
+        SentenceSplitter::default()
+    }
+
+    /// Chars that end a sentence, `。！？!?` by default.
+    pub fn terminators(mut self, terminators: &str) -> SentenceSplitter {
+        self.terminators = terminators.chars().collect();
+        self
+    }
+
+    /// Words such as `Mr.` whose final terminator does not end a sentence.
+    pub fn abbreviations<T: Into<String>, I: IntoIterator<Item = T>>(mut self, abbreviations: I) -> SentenceSplitter {
+        self.abbreviations = abbreviations.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Ends sentences at line breaks outside of quotes, enabled by default.
+    pub fn split_lines(mut self, enable: bool) -> SentenceSplitter {
+        self.split_lines = enable;
+        self
+    }
+
+    pub fn split<'a>(&self, text: &'a str) -> Vec<Sentence<'a>> {
+        let chars: Vec<(usize, char)> = text.char_indices().collect();
+        let byte_at = |i: usize| chars.get(i).map_or(text.len(), |&(b, _)| b);
+        let offsets = OffsetMap::new(text);
+
+        let mut ends = Vec::new();
+        let mut depth = 0usize;
+        let mut current = 0;
+        let mut i = 0;
+
+        while i < chars.len() {
+            let c = chars[i].1;
+
+            if OPENING.contains(&c) {
+                if is_closed(&chars, i) {
+                    depth += 1;
+                }
+            } else if CLOSING.contains(&c) {
+                depth = depth.saturating_sub(1);
+                // 「…。」 ends a sentence unless text such as と follows the quote
+                let j = skip(&chars, i + 1, |c| CLOSING.contains(&c));
+                if depth == 0 && i > 0 && self.is_terminator(chars[i - 1].1) &&
+                   chars.get(j).is_none_or(|&(_, c)| c.is_whitespace() || OPENING.contains(&c)) {
+                    ends.push(byte_at(j));
+                    current = byte_at(j);
+                    i = j;
+                    continue;
+                }
+            } else if c == '\n' {
+                let blank = chars[i + 1..].iter().take_while(|&&(_, c)| c != '\n' && c.is_whitespace()).count();
+                if chars.get(i + 1 + blank).map(|&(_, c)| c) == Some('\n') {
+                    depth = 0;
+                }
+                if depth == 0 && self.split_lines {
+                    ends.push(byte_at(i));
+                    current = byte_at(i);
+                }
+            } else if depth == 0 && (self.is_terminator(c) || ELLIPSES.contains(&c)) {
+                let j = skip(&chars, i, |c| self.is_terminator(c) || ELLIPSES.contains(&c));
+                let run: Vec<char> = chars[i..j].iter().map(|&(_, c)| c).collect();
+                let ellipsis = run.iter().all(|c| ELLIPSES.contains(c)) || (run.len() > 1 && run.iter().all(|&c| c == '.'));
+                let abbreviation = self.abbreviations.iter().any(|a| text[current..byte_at(j)].ends_with(a.as_str()));
+
+                if !ellipsis && !abbreviation {
+                    let j = skip(&chars, j, |c| CLOSING.contains(&c));
+                    ends.push(byte_at(j));
+                    current = byte_at(j);
+                }
+                i = j;
+                continue;
+            }
+            i += 1;
+        }
+        ends.push(text.len());
+
+        let mut sentences = Vec::new();
+        let mut begin = 0;
+        for end in ends {
+            if end < begin {
+                continue;
+            }
+            let raw = &text[begin..end];
+            let start = begin + (raw.len() - raw.trim_start().len());
+            let stop = begin + raw.trim_end().len();
+            if start < stop {
+                let span = Span::new(start, stop);
+                sentences.push(Sentence {
+                    text: &text[start..stop],
+                    span,
+                    char_span: offsets.byte_span_to_char(span).unwrap(),
+                    utf16_span: offsets.byte_span_to_utf16(span).unwrap(),
+                });
+            }
+            begin = end;
+        }
+
+        sentences
+    }
+
+    fn is_terminator(&self, c: char) -> bool {
+        self.terminators.contains(&c)
+    }
+}
+
+fn skip<F: Fn(char) -> bool>(chars: &[(usize, char)], mut i: usize, f: F) -> usize {
+    while i < chars.len() && f(chars[i].1) {
+        i += 1;
+    }
+    i
+}
+
+// true if the bracket at `i` is closed before a blank line and within `MAX_BRACKET_SPAN` chars
+fn is_closed(chars: &[(usize, char)], i: usize) -> bool {
+    let kind = OPENING.iter().position(|&c| c == chars[i].1).unwrap();
+    let mut depth = 0;
+    let mut newline = false;
+
+    for &(_, c) in chars.iter().skip(i + 1).take(MAX_BRACKET_SPAN) {
+        if c == OPENING[kind] {
+            depth += 1;
+        } else if c == CLOSING[kind] {
+            if depth == 0 {
+                return true;
+            }
+            depth -= 1;
+        } else if c == '\n' {
+            if newline {
+                return false;
+            }
+            newline = true;
+        } else if !c.is_whitespace() {
+            newline = false;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(text: &str) -> Vec<&str> {
+        SentenceSplitter::new().split(text).into_iter().map(|s| s.text).collect()
+    }
+
+    #[test]
+    fn terminators_split() {
+        assert_eq!(texts("今日は晴れ。明日は雨！本当？"), ["今日は晴れ。", "明日は雨！", "本当？"]);
+    }
+
+    #[test]
+    fn brackets_keep_sentences_together() {
+        assert_eq!(texts("彼は「行く。すぐ行く。」と言った。次の文。"),
+                   ["彼は「行く。すぐ行く。」と言った。", "次の文。"]);
+        assert_eq!(texts("「行く。」「来る。」"), ["「行く。」", "「来る。」"]);
+    }
+
+    #[test]
+    fn unclosed_bracket_is_ignored() {
+        assert_eq!(texts("（注：未完。次の文。さらに文。"), ["（注：未完。", "次の文。", "さらに文。"]);
+        // closed only after a blank line
+        assert_eq!(texts("「未完。次。\n\n」終わり。"), ["「未完。", "次。", "」終わり。"]);
+    }
+
+    #[test]
+    fn unclosed_bracket_beyond_span() {
+        let text = format!("「{}。い」", "あ".repeat(MAX_BRACKET_SPAN));
+        assert_eq!(texts(&text).len(), 2);
+    }
+
+    #[test]
+    fn spans_cover_the_sentence() {
+        let sentences = SentenceSplitter::new().split("一つ。 二つ。");
+        assert_eq!(sentences[1].text, "二つ。");
+        assert_eq!(sentences[1].span, Span::new(10, 19));
+        assert_eq!(sentences[1].char_span, Span::new(4, 7));
+    }
+}
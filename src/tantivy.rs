@@ -0,0 +1,240 @@
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use tantivy_tokenizer_api::{Token as IndexToken, TokenStream, Tokenizer};
+
+use decompound::{SearchMode, SearchToken};
+use mecab::Model;
+use pool::TaggerPool;
+use token::Token;
+
+/// Which text of a morpheme is indexed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenText {
+    Surface,
+    /// The lemma, so that inflected forms match each other.
+    BaseForm,
+    /// The katakana reading, the surface for unknown words.
+    Reading,
+}
+
+/// Tantivy tokenizer backed by a pool of taggers of one model.
+///
+/// Clones share the pool and can be used from any indexing thread.
+#[derive(Clone)]
+pub struct MecabTokenizer {
+    pool: Arc<TaggerPool>,
+    text: TokenText,
+    keep_pos: Vec<Vec<String>>,
+    stop_pos: Vec<Vec<String>>,
+    search_mode: Option<SearchMode>,
+}
+
+impl MecabTokenizer {
+    pub fn new(model: Model) -> MecabTokenizer {
+        MecabTokenizer::with_pool(Arc::new(TaggerPool::new(model)))
+    }
+
+    pub fn with_pool(pool: Arc<TaggerPool>) -> MecabTokenizer {
+        MecabTokenizer {
+            pool,
+            text: TokenText::Surface,
+            keep_pos: Vec::new(),
+            stop_pos: Vec::new(),
+            search_mode: None,
+        }
+    }
+
+    pub fn token_text(mut self, text: TokenText) -> MecabTokenizer {
+        self.text = text;
+        self
+    }
+
+    /// Also indexes the sub-words of long nouns.
+    pub fn search_mode(mut self, mode: Option<SearchMode>) -> MecabTokenizer {
+        self.search_mode = mode;
+        self
+    }
+
+    /// Only indexes morphemes matching one of the kept POS patterns.
+    pub fn keep_pos(mut self, pattern: &[&str]) -> MecabTokenizer {
+        self.keep_pos.push(pattern.iter().map(|&field| field.to_owned()).collect());
+        self
+    }
+
+    /// Skips morphemes matching `pattern`, such as `["助詞"]` or `["記号"]`.
+    pub fn stop_pos(mut self, pattern: &[&str]) -> MecabTokenizer {
+        self.stop_pos.push(pattern.iter().map(|&field| field.to_owned()).collect());
+        self
+    }
+
+    fn is_indexed(&self, token: &Token) -> bool {
+        (self.keep_pos.is_empty() || self.keep_pos.iter().any(|p| token.matches_pos(p))) &&
+        !self.stop_pos.iter().any(|p| token.matches_pos(p))
+    }
+
+    fn text_of(&self, token: &Token) -> String {
+        match self.text {
+            TokenText::Surface => token.surface.clone(),
+            TokenText::BaseForm => token.lemma().to_owned(),
+            TokenText::Reading => token.reading().unwrap_or(&token.surface).to_owned(),
+        }
+    }
+}
+
+impl Tokenizer for MecabTokenizer {
+    type TokenStream<'a> = MecabTokenStream;
+
+    fn token_stream<'a>(&'a mut self, text: &'a str) -> MecabTokenStream {
+        // libmecab reads the sentence as a C string, a space in place of NUL keeps the byte offsets
+        let text = if text.contains('\0') {
+            Cow::Owned(text.replace('\0', " "))
+        } else {
+            Cow::Borrowed(text)
+        };
+        let mut tagger = self.pool.get();
+        tagger.lattice_mut().set_sentence(text.as_ref());
+        let tokens = if !tagger.parse(tagger.lattice()) {
+            Vec::new()
+        } else if let Some(mode) = self.search_mode {
+            mode.tokens(tagger.lattice(), self.pool.model())
+        } else {
+            tagger.lattice()
+                .tokens()
+                .into_iter()
+                .enumerate()
+                .map(|(position, token)| {
+                    SearchToken {
+                        token,
+                        position,
+                        position_length: 1,
+                    }
+                })
+                .collect()
+        };
+
+        // filtered morphemes keep their position so that phrase queries stay exact
+        let tokens = tokens.iter()
+            .filter(|t| self.is_indexed(&t.token))
+            .map(|t| {
+                IndexToken {
+                    offset_from: t.token.span.begin,
+                    offset_to: t.token.span.end,
+                    position: t.position,
+                    text: self.text_of(&t.token),
+                    position_length: t.position_length,
+                }
+            })
+            .collect();
+
+        MecabTokenStream {
+            tokens,
+            index: None,
+        }
+    }
+}
+
+pub struct MecabTokenStream {
+    tokens: Vec<IndexToken>,
+    index: Option<usize>,
+}
+
+impl TokenStream for MecabTokenStream {
+    fn advance(&mut self) -> bool {
+        let next = self.index.map_or(0, |i| i + 1);
+        self.index = Some(next.min(self.tokens.len()));
+        next < self.tokens.len()
+    }
+
+    fn token(&self) -> &IndexToken {
+        &self.tokens[self.index.expect("advance has not been called")]
+    }
+
+    fn token_mut(&mut self) -> &mut IndexToken {
+        &mut self.tokens[self.index.expect("advance has not been called")]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokenizer() -> MecabTokenizer {
+        MecabTokenizer::new(Model::mock(&[("猫", "名詞,一般,*,*,*,*,猫,ネコ,ネコ"),
+                                          ("が", "助詞,格助詞,一般,*,*,*,が,ガ,ガ"),
+                                          ("走っ", "動詞,自立,*,*,五段・ラ行,連用タ接続,走る,ハシッ,ハシッ"),
+                                          ("た", "助動詞,*,*,*,特殊・タ,基本形,た,タ,タ"),
+                                          ("。", "記号,句点,*,*,*,*,。,。,。")]))
+    }
+
+    fn tokens(tokenizer: &mut MecabTokenizer, text: &str) -> Vec<(String, usize, usize, usize)> {
+        let mut stream = tokenizer.token_stream(text);
+        let mut tokens = Vec::new();
+        while stream.advance() {
+            let t = stream.token();
+            tokens.push((t.text.clone(), t.offset_from, t.offset_to, t.position));
+        }
+        tokens
+    }
+
+    fn token(text: &str, offset_from: usize, offset_to: usize, position: usize) -> (String, usize, usize, usize) {
+        (text.to_owned(), offset_from, offset_to, position)
+    }
+
+    #[test]
+    fn offsets_and_positions() {
+        assert_eq!(tokens(&mut tokenizer(), "猫が走った。"),
+                   [token("猫", 0, 3, 0),
+                    token("が", 3, 6, 1),
+                    token("走っ", 6, 12, 2),
+                    token("た", 12, 15, 3),
+                    token("。", 15, 18, 4)]);
+        assert!(tokens(&mut tokenizer(), "").is_empty());
+    }
+
+    #[test]
+    fn filtered_morphemes_keep_positions() {
+        let mut tokenizer = tokenizer().stop_pos(&["助詞"]).stop_pos(&["記号"]);
+        assert_eq!(tokens(&mut tokenizer, "猫が走った。"),
+                   [token("猫", 0, 3, 0), token("走っ", 6, 12, 2), token("た", 12, 15, 3)]);
+
+        let mut tokenizer = self::tokenizer().keep_pos(&["名詞"]).keep_pos(&["動詞", "自立"]);
+        assert_eq!(tokens(&mut tokenizer, "猫が走った。"),
+                   [token("猫", 0, 3, 0), token("走っ", 6, 12, 2)]);
+
+        let mut tokenizer = self::tokenizer().keep_pos(&["動詞"]).stop_pos(&["*", "自立"]);
+        assert!(tokens(&mut tokenizer, "猫が走った。").is_empty());
+    }
+
+    #[test]
+    fn token_text() {
+        let mut tokenizer = tokenizer().token_text(TokenText::BaseForm);
+        let texts: Vec<String> = tokens(&mut tokenizer, "猫が走った").into_iter().map(|t| t.0).collect();
+        assert_eq!(texts, ["猫", "が", "走る", "た"]);
+
+        // unknown words have no reading
+        let mut tokenizer = self::tokenizer().token_text(TokenText::Reading);
+        let texts: Vec<String> = tokens(&mut tokenizer, "猫が犬").into_iter().map(|t| t.0).collect();
+        assert_eq!(texts, ["ネコ", "ガ", "犬"]);
+    }
+
+    #[test]
+    fn nul_keeps_offsets() {
+        assert_eq!(tokens(&mut tokenizer(), "猫\0が"), [token("猫", 0, 3, 0), token("が", 4, 7, 1)]);
+        assert_eq!(tokens(&mut tokenizer(), "\0猫\0"), [token("猫", 1, 4, 0)]);
+        assert_eq!(tokens(&mut tokenizer(), "\0"), []);
+    }
+
+    #[test]
+    fn stream() {
+        let mut tokenizer = tokenizer();
+        let mut stream = tokenizer.token_stream("猫が");
+        assert!(stream.advance());
+        stream.token_mut().text.push('!');
+        assert_eq!(stream.token().text, "猫!");
+        assert!(stream.advance());
+        assert_eq!(stream.token().text, "が");
+        assert!(!stream.advance());
+        assert!(!stream.advance());
+    }
+}
@@ -0,0 +1,225 @@
+use std::os::raw::c_long;
+
+use mecab::{Lattice, Node, Tagger, MECAB_BOS_NODE, MECAB_EOS_NODE};
+use offset::OffsetMap;
+
+#[cfg(feature = "json")]
+use serde::{Deserialize, Serialize};
+
+/// Half-open range of offsets into a sentence.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "json", derive(Serialize, Deserialize))]
+pub struct Span {
+    pub begin: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(begin: usize, end: usize) -> Span {
+        Span { begin, end }
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.begin
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.begin == self.end
+    }
+}
+
+/// Owned copy of a morpheme node that stays valid after the lattice is reused.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "json", derive(Serialize, Deserialize))]
+pub struct Token {
+    pub surface: String,
+    pub feature: String,
+    /// Byte span of the surface within the sentence.
+    pub span: Span,
+    pub char_span: Span,
+    pub utf16_span: Span,
+    pub stat: u8,
+    pub posid: u16,
+    pub lcattr: u16,
+    pub rcattr: u16,
+    pub wcost: i16,
+    pub cost: i64,
+    pub prob: f32,
+}
+
+impl Token {
+    /// Builds a token from a node of the sentence described by `offsets`.
+    pub fn from_node(node: &Node, offsets: &OffsetMap) -> Token {
+        // the node surface runs until the end of the sentence
+        let begin = offsets.byte_len() - node.surface.len();
+        let length = node.length as usize;
+        let span = Span::new(begin, begin + length);
+
+        Token {
+            surface: node.surface[..length].to_owned(),
+            feature: node.feature.clone(),
+            span,
+            char_span: offsets.byte_span_to_char(span).unwrap(),
+            utf16_span: offsets.byte_span_to_utf16(span).unwrap(),
+            stat: node.stat,
+            posid: node.posid,
+            lcattr: node.lcattr,
+            rcattr: node.rcattr,
+            wcost: node.wcost,
+            cost: long_to_i64(node.cost),
+            prob: node.prob,
+        }
+    }
+
+    /// Splits the feature CSV into its fields, removing quotes around fields that contain commas.
+    pub fn features(&self) -> Vec<&str> {
+        split_features(&self.feature)
+    }
+
+    pub fn feature_at(&self, index: usize) -> Option<&str> {
+        self.features().get(index).cloned()
+    }
+
+    /// Top-level part of speech, the first feature field.
+    pub fn pos(&self) -> &str {
+        self.features()[0]
+    }
+
+    /// True if the feature starts with `pattern`, `*` matches any field.
+    pub fn matches_pos<S: AsRef<str>>(&self, pattern: &[S]) -> bool {
+        let features = self.features();
+        pattern.iter()
+            .enumerate()
+            .all(|(i, field)| field.as_ref() == "*" || features.get(i) == Some(&field.as_ref()))
+    }
+
+    /// Katakana reading from the IPADIC feature, `None` for unknown words.
+    pub fn reading(&self) -> Option<&str> {
+        self.feature_at(7).filter(|r| *r != "*")
+    }
+
+    /// Katakana pronunciation from the IPADIC feature, `None` for unknown words.
+    pub fn pronunciation(&self) -> Option<&str> {
+        self.feature_at(8).filter(|r| *r != "*")
+    }
+
+    /// Base form from the IPADIC feature, the surface for words without one.
+    pub fn lemma(&self) -> &str {
+        match self.feature_at(6) {
+            Some(base) if base != "*" => base,
+            _ => &self.surface,
+        }
+    }
+
+    /// Conjugation type such as `五段・カ行イ音便`, `None` for words that do not conjugate.
+    pub fn conjugation_type(&self) -> Option<&str> {
+        self.feature_at(4).filter(|c| *c != "*")
+    }
+
+    /// Conjugation form such as `連用タ接続`, `None` for words that do not conjugate.
+    pub fn conjugation_form(&self) -> Option<&str> {
+        self.feature_at(5).filter(|c| *c != "*")
+    }
+}
+
+pub fn split_features(feature: &str) -> Vec<&str> {
+    let mut fields = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+
+    for (i, c) in feature.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                fields.push(unquote(&feature[start..i]));
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    fields.push(unquote(&feature[start..]));
+
+    fields
+}
+
+fn unquote(field: &str) -> &str {
+    if field.len() >= 2 && field.starts_with('"') && field.ends_with('"') {
+        &field[1..field.len() - 1]
+    } else {
+        field
+    }
+}
+
+// c_long is only 32 bits wide on Windows
+#[allow(clippy::unnecessary_cast)]
+pub(crate) fn long_to_i64(value: c_long) -> i64 {
+    value as i64
+}
+
+pub(crate) fn is_boundary_node(node: &Node) -> bool {
+    node.stat as i32 == MECAB_BOS_NODE || node.stat as i32 == MECAB_EOS_NODE
+}
+
+impl Lattice {
+    /// Tokens of the current best (or n-best) path without BOS and EOS.
+    pub fn tokens(&self) -> Vec<Token> {
+        let offsets = OffsetMap::new(&self.sentence());
+        path_tokens(self.bos_node(), &offsets)
+    }
+}
+
+impl Tagger {
+    /// Parses `input` like `parse_to_node` and returns the tokens of the best path.
+    ///
+    /// Invalid UTF-8 is replaced with U+FFFD before parsing, so that the token spans stay valid.
+    pub fn parse_to_tokens<T: Into<Vec<u8>>>(&mut self, input: T) -> Vec<Token> {
+        let input = match String::from_utf8(input.into()) {
+            Ok(input) => input,
+            Err(e) => String::from_utf8_lossy(e.as_bytes()).into_owned(),
+        };
+        let offsets = OffsetMap::new(&input);
+        path_tokens(self.parse_to_node(input), &offsets)
+    }
+}
+
+fn path_tokens(bos: Node, offsets: &OffsetMap) -> Vec<Token> {
+    bos.iter_next()
+        .filter(|node| !is_boundary_node(node))
+        .map(|node| Token::from_node(&node, offsets))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_node_spans() {
+        let offsets = OffsetMap::new("すもももも");
+        // the second token starts after すもも, its surface runs to the end of the sentence
+        let token = Token::from_node(&Node::mock("もも", 3, "助詞,係助詞,*,*,*,*,も,モ,モ"), &offsets);
+
+        assert_eq!(token.surface, "も");
+        assert_eq!(token.span, Span::new(9, 12));
+        assert_eq!(token.char_span, Span::new(3, 4));
+        assert_eq!(token.utf16_span, Span::new(3, 4));
+        assert_eq!(token.feature, "助詞,係助詞,*,*,*,*,も,モ,モ");
+    }
+
+    #[test]
+    fn from_node_spans_astral() {
+        let offsets = OffsetMap::new("𠮷野家");
+        let token = Token::from_node(&Node::mock("野家", 6, "名詞,固有名詞,組織,*,*,*,*"), &offsets);
+
+        assert_eq!(token.span, Span::new(4, 10));
+        assert_eq!(token.char_span, Span::new(1, 3));
+        assert_eq!(token.utf16_span, Span::new(2, 4));
+    }
+
+    #[test]
+    fn from_node_first_and_last() {
+        let offsets = OffsetMap::new("すもも");
+        assert_eq!(Token::from_node(&Node::mock("すもも", 9, "名詞"), &offsets).span, Span::new(0, 9));
+        assert_eq!(Token::from_node(&Node::mock("も", 3, "名詞"), &offsets).span, Span::new(6, 9));
+    }
+}
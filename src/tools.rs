@@ -0,0 +1,659 @@
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::os::raw::{c_char, c_int};
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use mecab::{argv, c_args};
+
+#[link(name="mecab")]
+extern "C" {
+    fn mecab_dict_index(argc: c_int, argv: *mut *mut c_char) -> c_int;
+    fn mecab_dict_gen(argc: c_int, argv: *mut *mut c_char) -> c_int;
+    fn mecab_cost_train(argc: c_int, argv: *mut *mut c_char) -> c_int;
+    fn mecab_system_eval(argc: c_int, argv: *mut *mut c_char) -> c_int;
+    fn mecab_test_gen(argc: c_int, argv: *mut *mut c_char) -> c_int;
+}
+
+type ToolMain = unsafe extern "C" fn(c_int, *mut *mut c_char) -> c_int;
+
+// names the tool a child process started by `Args::run` has to run
+const TOOL_VAR: &str = "MECAB_RS_TOOL";
+
+static ENTRY_POINT: AtomicBool = AtomicBool::new(false);
+
+fn tool_main(program: &str) -> Option<ToolMain> {
+    match program {
+        "mecab-dict-index" => Some(mecab_dict_index),
+        "mecab-dict-gen" => Some(mecab_dict_gen),
+        "mecab-cost-train" => Some(mecab_cost_train),
+        "mecab-system-eval" => Some(mecab_system_eval),
+        "mecab-test-gen" => Some(mecab_test_gen),
+        _ => None,
+    }
+}
+
+/// Runs the libmecab tool a child process was started for and exits, returns
+/// right away in any other process.
+///
+/// The tools run in a fresh process of the current executable, so programs
+/// using `DictIndex`, `DictGen`, `CostTrain`, `SystemEval` or `TestGen` have
+/// to call this first thing in `main`, before any thread is started. Their
+/// `run()` fails with `ToolError::Io` otherwise.
+pub fn tool_entry_point() {
+    ENTRY_POINT.store(true, Ordering::SeqCst);
+    let program = match env::var(TOOL_VAR) {
+        Ok(program) => program,
+        Err(_) => return,
+    };
+    let main = match tool_main(&program) {
+        Some(main) => main,
+        None => {
+            eprintln!("unknown libmecab tool {:?}", program);
+            process::exit(127);
+        }
+    };
+    let args = c_args(&program, env::args().skip(1)).unwrap_or_else(|arg| {
+        eprintln!("invalid argument {:?}", arg);
+        process::exit(127);
+    });
+    let mut argv = argv(&args);
+    process::exit(unsafe { main(argv.len() as c_int, argv.as_mut_ptr()) });
+}
+
+/// Exit status and captured output of a libmecab tool.
+///
+/// The tool runs in a child process of the current executable, see
+/// `tool_entry_point`, so its output is kept apart from other threads and a
+/// fatal error inside libmecab only ends the child. `status` is the exit status of the child, 128 plus
+/// the signal number if it was killed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ToolOutput {
+    pub status: i32,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+#[derive(Debug)]
+pub enum ToolError {
+    /// An input file or directory does not exist.
+    NotFound(PathBuf),
+    /// An argument contains a NUL byte.
+    InvalidArgument(String),
+    /// Starting the tool failed, such as when `main` does not call `tool_entry_point`.
+    Io(io::Error),
+    /// The tool returned a non-zero status.
+    Failed(ToolOutput),
+}
+
+impl fmt::Display for ToolError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ToolError::NotFound(path) => write!(f, "{} does not exist", path.display()),
+            ToolError::InvalidArgument(arg) => write!(f, "invalid argument {:?}", arg),
+            ToolError::Io(e) => write!(f, "{}", e),
+            ToolError::Failed(output) => {
+                let log = if output.stderr.trim().is_empty() { &output.stdout } else { &output.stderr };
+                write!(f, "exited with status {}", output.status)?;
+                match log.lines().rev().find(|l| !l.trim().is_empty()) {
+                    Some(line) => write!(f, ": {}", line.trim()),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+impl Error for ToolError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ToolError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ToolError {
+    fn from(e: io::Error) -> ToolError {
+        ToolError::Io(e)
+    }
+}
+
+/// Command line of a tool, options are passed as `--name=value`.
+#[derive(Clone, Debug, Default)]
+struct Args {
+    options: Vec<String>,
+    inputs: Vec<PathBuf>,
+}
+
+impl Args {
+    fn set<T: fmt::Display>(&mut self, name: &str, value: T) {
+        let prefix = format!("--{}=", name);
+        self.options.retain(|o| !o.starts_with(&prefix));
+        self.options.push(format!("{}{}", prefix, value));
+    }
+
+    fn flag(&mut self, name: &str, enabled: bool) {
+        let flag = format!("--{}", name);
+        self.options.retain(|o| *o != flag);
+        if enabled {
+            self.options.push(flag);
+        }
+    }
+
+    fn path<P: AsRef<Path>>(&mut self, name: &str, path: P) {
+        self.set(name, path.as_ref().display());
+    }
+
+    fn require<P: AsRef<Path>>(&mut self, path: P) {
+        self.inputs.push(path.as_ref().to_path_buf());
+    }
+
+    // libmecab exits the process on missing inputs, so they are checked beforehand
+    fn run(&self, program: &str, positional: &[&Path]) -> Result<ToolOutput, ToolError> {
+        if let Some(missing) = self.inputs.iter().find(|p| !p.exists()) {
+            return Err(ToolError::NotFound(missing.clone()));
+        }
+
+        let args: Vec<String> = self.options
+            .iter()
+            .cloned()
+            .chain(positional.iter().map(|p| p.display().to_string()))
+            .collect();
+        c_args(program, &args).map_err(ToolError::InvalidArgument)?;
+
+        if !ENTRY_POINT.load(Ordering::SeqCst) {
+            return Err(ToolError::Io(io::Error::other("tool_entry_point() has to be called at the start of main")));
+        }
+        let mut command = Command::new(env::current_exe()?);
+        command.env(TOOL_VAR, program).args(&args);
+
+        let output = capture(command)?;
+        if output.status == 0 {
+            Ok(output)
+        } else {
+            Err(ToolError::Failed(output))
+        }
+    }
+}
+
+// runs `command` to its end with stdin closed and both output streams captured
+fn capture(mut command: Command) -> io::Result<ToolOutput> {
+    let output = command.stdin(Stdio::null()).output()?;
+    Ok(ToolOutput {
+        status: exit_status(output.status),
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+    })
+}
+
+#[cfg(unix)]
+fn exit_status(status: ExitStatus) -> i32 {
+    use std::os::unix::process::ExitStatusExt;
+
+    status.code().unwrap_or_else(|| 128 + status.signal().unwrap_or(0))
+}
+
+#[cfg(not(unix))]
+fn exit_status(status: ExitStatus) -> i32 {
+    status.code().unwrap_or(-1)
+}
+
+/// Compiles the CSV sources of a dictionary into a system or user dictionary,
+/// like `mecab-dict-index`.
+///
+/// Fatal errors inside libmecab, such as malformed CSV lines, are reported as
+/// `ToolError::Failed`.
+#[derive(Clone, Debug)]
+pub struct DictIndex {
+    args: Args,
+}
+
+impl DictIndex {
+    /// Builds the system dictionary of the sources in `dicdir` into `outdir`.
+    pub fn new<P: AsRef<Path>, Q: AsRef<Path>>(dicdir: P, outdir: Q) -> DictIndex {
+        let mut args = Args::default();
+        args.require(&dicdir);
+        args.path("dicdir", dicdir);
+        args.path("outdir", outdir);
+        DictIndex { args }
+    }
+
+    /// Builds a user dictionary at `file` from the CSV files passed to `csv`.
+    pub fn userdic<P: AsRef<Path>>(mut self, file: P) -> DictIndex {
+        self.args.path("userdic", file);
+        self
+    }
+
+    pub fn csv<P: AsRef<Path>>(mut self, file: P) -> DictIndex {
+        self.args.require(&file);
+        self.args.options.push(file.as_ref().display().to_string());
+        self
+    }
+
+    /// Assigns costs to entries without one using a trained model.
+    pub fn model<P: AsRef<Path>>(mut self, file: P) -> DictIndex {
+        self.args.require(&file);
+        self.args.path("model", file);
+        self
+    }
+
+    /// Charset of the CSV sources.
+    pub fn dictionary_charset(mut self, charset: &str) -> DictIndex {
+        self.args.set("dictionary-charset", charset);
+        self
+    }
+
+    /// Charset of the compiled dictionary.
+    pub fn charset(mut self, charset: &str) -> DictIndex {
+        self.args.set("charset", charset);
+        self
+    }
+
+    /// Assigns POS ids from `pos-id.def`.
+    pub fn posid(mut self, posid: bool) -> DictIndex {
+        self.args.flag("posid", posid);
+        self
+    }
+
+    /// Builds a dictionary that only segments.
+    pub fn wakati(mut self, wakati: bool) -> DictIndex {
+        self.args.flag("wakati", wakati);
+        self
+    }
+
+    /// Only assigns costs and ids to the user dictionary CSV instead of compiling it.
+    pub fn assign_user_dictionary_costs(mut self, assign: bool) -> DictIndex {
+        self.args.flag("assign-user-dictionary-costs", assign);
+        self
+    }
+
+    /// Passes any other `mecab-dict-index` option such as `--node-format=...`.
+    pub fn arg<T: Into<String>>(mut self, arg: T) -> DictIndex {
+        self.args.options.push(arg.into());
+        self
+    }
+
+    pub fn run(&self) -> Result<ToolOutput, ToolError> {
+        self.args.run("mecab-dict-index", &[])
+    }
+}
+
+/// Writes the sources of a dictionary with the costs of a trained model,
+/// like `mecab-dict-gen`.
+#[derive(Clone, Debug)]
+pub struct DictGen {
+    args: Args,
+}
+
+impl DictGen {
+    pub fn new<P: AsRef<Path>, Q: AsRef<Path>, R: AsRef<Path>>(dicdir: P, model: Q, outdir: R) -> DictGen {
+        let mut args = Args::default();
+        args.require(&dicdir);
+        args.require(&model);
+        args.path("dicdir", dicdir);
+        args.path("model", model);
+        args.path("outdir", outdir);
+        DictGen { args }
+    }
+
+    pub fn arg<T: Into<String>>(mut self, arg: T) -> DictGen {
+        self.args.options.push(arg.into());
+        self
+    }
+
+    pub fn run(&self) -> Result<ToolOutput, ToolError> {
+        self.args.run("mecab-dict-gen", &[])
+    }
+}
+
+/// One iteration reported by `mecab-cost-train`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TrainingIteration {
+    pub iteration: usize,
+    /// Fraction of misanalyzed sentences.
+    pub error_rate: f64,
+    pub f: f64,
+    pub target: f64,
+    pub diff: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrainingReport {
+    pub iterations: Vec<TrainingIteration>,
+    pub output: ToolOutput,
+}
+
+/// Trains a CRF model from an annotated corpus, like `mecab-cost-train`.
+#[derive(Clone, Debug)]
+pub struct CostTrain {
+    args: Args,
+    corpus: PathBuf,
+    model: PathBuf,
+}
+
+impl CostTrain {
+    /// Trains on `corpus` with the dictionary sources in `dicdir` and writes the model to `model`.
+    pub fn new<P: AsRef<Path>, Q: AsRef<Path>, R: AsRef<Path>>(dicdir: P, corpus: Q, model: R) -> CostTrain {
+        let mut args = Args::default();
+        args.require(&dicdir);
+        args.require(&corpus);
+        args.path("dicdir", dicdir);
+        CostTrain {
+            args,
+            corpus: corpus.as_ref().to_path_buf(),
+            model: model.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Continues training from an existing model.
+    pub fn old_model<P: AsRef<Path>>(mut self, file: P) -> CostTrain {
+        self.args.require(&file);
+        self.args.path("old-model", file);
+        self
+    }
+
+    /// Trade-off between fitting the corpus and regularization, 1.0 by default.
+    pub fn cost(mut self, cost: f64) -> CostTrain {
+        self.args.set("cost", cost);
+        self
+    }
+
+    /// Ignores features seen fewer than `freq` times, 1 by default.
+    pub fn freq(mut self, freq: usize) -> CostTrain {
+        self.args.set("freq", freq);
+        self
+    }
+
+    /// Tolerance of the termination criterion.
+    pub fn eta(mut self, eta: f64) -> CostTrain {
+        self.args.set("eta", eta);
+        self
+    }
+
+    pub fn threads(mut self, threads: usize) -> CostTrain {
+        self.args.set("thread", threads.max(1));
+        self
+    }
+
+    /// Also writes a binary model next to the text model.
+    pub fn build(mut self, build: bool) -> CostTrain {
+        self.args.flag("build", build);
+        self
+    }
+
+    pub fn arg<T: Into<String>>(mut self, arg: T) -> CostTrain {
+        self.args.options.push(arg.into());
+        self
+    }
+
+    pub fn run(&self) -> Result<TrainingReport, ToolError> {
+        let output = self.args.run("mecab-cost-train", &[&self.corpus, &self.model])?;
+        Ok(TrainingReport {
+            iterations: output.stdout.lines().filter_map(parse_iteration).collect(),
+            output,
+        })
+    }
+}
+
+// `iter=  3 err=0.12 F=0.97 target=1234.5 diff=0.01`
+fn parse_iteration(line: &str) -> Option<TrainingIteration> {
+    if !line.starts_with("iter=") {
+        return None;
+    }
+
+    let mut values = Vec::new();
+    let mut tokens = line.split_whitespace();
+    while let Some(token) = tokens.next() {
+        let (key, value) = token.split_once('=')?;
+        let value = if value.is_empty() { tokens.next()? } else { value };
+        values.push((key, value));
+    }
+    let value = |key: &str| values.iter().find(|v| v.0 == key).and_then(|v| v.1.parse::<f64>().ok());
+
+    Some(TrainingIteration {
+        iteration: value("iter")? as usize,
+        error_rate: value("err")?,
+        f: value("F")?,
+        target: value("target")?,
+        diff: value("diff")?,
+    })
+}
+
+/// Precision and recall at one level of `mecab-system-eval`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EvaluationLevel {
+    /// Number of compared feature fields, `None` for the whole feature.
+    pub level: Option<i32>,
+    pub precision: f64,
+    pub recall: f64,
+    pub f: f64,
+    pub correct: usize,
+    /// Tokens in the system output.
+    pub system: usize,
+    /// Tokens in the answer.
+    pub answer: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Evaluation {
+    pub levels: Vec<EvaluationLevel>,
+    pub output: ToolOutput,
+}
+
+/// Compares system output with an answer in mecab format, like `mecab-system-eval`.
+#[derive(Clone, Debug)]
+pub struct SystemEval {
+    args: Args,
+    system: PathBuf,
+    answer: PathBuf,
+}
+
+impl SystemEval {
+    pub fn new<P: AsRef<Path>, Q: AsRef<Path>>(system: P, answer: Q) -> SystemEval {
+        let mut args = Args::default();
+        args.require(&system);
+        args.require(&answer);
+        SystemEval {
+            args,
+            system: system.as_ref().to_path_buf(),
+            answer: answer.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Levels to evaluate, `-1` compares the whole feature. `0 -1` by default.
+    pub fn levels(mut self, levels: &[i32]) -> SystemEval {
+        let levels: Vec<String> = levels.iter().map(|l| l.to_string()).collect();
+        self.args.set("level", levels.join(" "));
+        self
+    }
+
+    pub fn run(&self) -> Result<Evaluation, ToolError> {
+        let output = self.args.run("mecab-system-eval", &[&self.system, &self.answer])?;
+        Ok(Evaluation {
+            levels: output.stdout.lines().filter_map(parse_level).collect(),
+            output,
+        })
+    }
+}
+
+// `LEVEL 0:    98.12(1234/1258) 97.50(1234/1266) 97.81` or `LEVEL ALL: ...`
+fn parse_level(line: &str) -> Option<EvaluationLevel> {
+    let (label, rest) = line.strip_prefix("LEVEL ")?.split_once(':')?;
+    let level = match label.trim() {
+        "ALL" => None,
+        n => Some(n.parse().ok()?),
+    };
+
+    let ratio = |field: &str| -> Option<(f64, usize, usize)> {
+        let (value, counts) = field.split_once('(')?;
+        let (n, d) = counts.trim_end_matches(')').split_once('/')?;
+        Some((value.parse().ok()?, n.parse().ok()?, d.parse().ok()?))
+    };
+    let fields: Vec<&str> = rest.split_whitespace().collect();
+    if fields.len() != 3 {
+        return None;
+    }
+    let (precision, correct, system) = ratio(fields[0])?;
+    let (recall, _, answer) = ratio(fields[1])?;
+
+    Some(EvaluationLevel {
+        level,
+        precision,
+        recall,
+        f: fields[2].parse().ok()?,
+        correct,
+        system,
+        answer,
+    })
+}
+
+/// Turns a corpus in mecab format back into raw sentences, like `mecab-test-gen`.
+#[derive(Clone, Debug)]
+pub struct TestGen {
+    args: Args,
+    input: PathBuf,
+}
+
+impl TestGen {
+    pub fn new<P: AsRef<Path>>(input: P) -> TestGen {
+        let mut args = Args::default();
+        args.require(&input);
+        TestGen {
+            args,
+            input: input.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Writes the sentences to `file` instead of `ToolOutput::stdout`.
+    pub fn output<P: AsRef<Path>>(mut self, file: P) -> TestGen {
+        self.args.path("output", file);
+        self
+    }
+
+    pub fn run(&self) -> Result<ToolOutput, ToolError> {
+        self.args.run("mecab-test-gen", &[&self.input])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    fn sh(script: &str) -> ToolOutput {
+        let mut command = Command::new("/bin/sh");
+        command.arg("-c").arg(script);
+        capture(command).unwrap()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn capture_streams() {
+        assert_eq!(sh("echo out; echo err >&2"),
+                   ToolOutput {
+                       status: 0,
+                       stdout: "out\n".to_owned(),
+                       stderr: "err\n".to_owned(),
+                   });
+        assert_eq!(sh("read line || echo closed").stdout, "closed\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn capture_survives_exit() {
+        // libmecab calls exit() on fatal errors, only the child ends
+        let output = sh("echo fatal >&2; exit 3");
+        assert_eq!(output.status, 3);
+        assert_eq!(output.stderr, "fatal\n");
+        assert_eq!(sh("kill -9 $$").status, 128 + 9);
+    }
+
+    #[test]
+    fn invalid_argument() {
+        let mut args = Args::default();
+        args.set("model", "a\0b");
+        match args.run("mecab-test-gen", &[]) {
+            Err(ToolError::InvalidArgument(arg)) => assert_eq!(arg, "--model=a\0b"),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn needs_entry_point() {
+        // the test harness does not call `tool_entry_point`
+        match Args::default().run("mecab-test-gen", &[]) {
+            Err(ToolError::Io(e)) => assert!(e.to_string().contains("tool_entry_point")),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn iterations() {
+        let stdout = "reading corpus ...\n\
+                      Number of sentences: 3\n\
+                      eta:                 0.00010\n\
+                      \n\
+                      iter=0 err=1.00000 F=0.35714 target=2406.60498 diff=1.00000\n\
+                      iter=  3 err=0.33333 F=0.92857 target=310.25713 diff=0.02132\n\
+                      iter=4 err=0.00000 F=1.00000\n\
+                      iter=x err=0.1 F=0.9 target=1 diff=0.1\n\
+                      iter=5 err F=0.9 target=1 diff=0.1\n\
+                      \n\
+                      Done! writing model file ... \n";
+        let iterations: Vec<TrainingIteration> = stdout.lines().filter_map(parse_iteration).collect();
+        assert_eq!(iterations,
+                   [TrainingIteration {
+                        iteration: 0,
+                        error_rate: 1.0,
+                        f: 0.35714,
+                        target: 2406.60498,
+                        diff: 1.0,
+                    },
+                    TrainingIteration {
+                        iteration: 3,
+                        error_rate: 0.33333,
+                        f: 0.92857,
+                        target: 310.25713,
+                        diff: 0.02132,
+                    }]);
+    }
+
+    #[test]
+    fn levels() {
+        let stdout = "              precision          recall         F\n\
+                      LEVEL 0:    100.0000(8/8) 88.8889(8/9) 94.1176\n\
+                      LEVEL -1:    75.0000(6/8) 66.6667(6/9) 70.5882\n\
+                      LEVEL ALL: 75.0000(6/8) 66.6667(6/9) 70.5882\n\
+                      LEVEL 1: 75.0000(6/8) 66.6667(6/9)\n\
+                      LEVEL 2: 75.0000(6/8) 66.6667 70.5882\n\
+                      LEVEL x: 75.0000(6/8) 66.6667(6/9) 70.5882\n\
+                      LEVEL 3 75.0000(6/8) 66.6667(6/9) 70.5882\n";
+        let levels: Vec<EvaluationLevel> = stdout.lines().filter_map(parse_level).collect();
+        assert_eq!(levels[0],
+                   EvaluationLevel {
+                       level: Some(0),
+                       precision: 100.0,
+                       recall: 88.8889,
+                       f: 94.1176,
+                       correct: 8,
+                       system: 8,
+                       answer: 9,
+                   });
+        assert_eq!(levels.iter().map(|l| l.level).collect::<Vec<_>>(), [Some(0), Some(-1), None]);
+        assert_eq!(levels[1].recall, 66.6667);
+        assert_eq!((levels[2].correct, levels[2].system, levels[2].answer), (6, 8, 9));
+    }
+
+    #[test]
+    fn level_argument() {
+        // libmecab reads the levels from a single option separated by spaces
+        let eval = SystemEval::new("system.txt", "answer.txt").levels(&[0, -1]);
+        assert_eq!(eval.args.options, ["--level=0 -1"]);
+        let eval = eval.levels(&[2]);
+        assert_eq!(eval.args.options, ["--level=2"]);
+    }
+}
@@ -0,0 +1,24 @@
+#![allow(dead_code)]
+
+use std::env;
+
+use mecab::Model;
+
+/// libmecab arguments for the tests, `-d $MECAB_TEST_DICDIR` if it is set.
+pub fn args() -> Vec<String> {
+    match env::var("MECAB_TEST_DICDIR") {
+        Ok(dicdir) => vec!["-d".to_owned(), dicdir],
+        Err(_) => Vec::new(),
+    }
+}
+
+/// The default model, `None` after printing a notice if no dictionary is installed.
+pub fn model() -> Option<Model> {
+    let model = Model::with_args(&args());
+    if model.is_available() {
+        Some(model)
+    } else {
+        eprintln!("skipping: libmecab has no dictionary, set MECAB_TEST_DICDIR to run this test");
+        None
+    }
+}
@@ -0,0 +1,24 @@
+extern crate mecab;
+
+mod common;
+
+#[test]
+fn compound_noun_is_split() {
+    let model = match common::model() {
+        Some(model) => model,
+        None => return,
+    };
+
+    let tagger = model.create_tagger();
+    let mut lattice = model.create_lattice();
+    lattice.set_sentence("関西国際空港");
+    assert!(tagger.parse(&lattice));
+    let tokens = lattice.search_tokens(&model);
+
+    // the dictionary has the whole compound, which must not be its own only part
+    assert_eq!(tokens[0].token.surface, "関西国際空港");
+    assert!(tokens[0].position_length >= 2);
+    let parts: Vec<&str> = tokens[1..].iter().map(|t| t.token.surface.as_str()).collect();
+    assert_eq!(parts.len(), tokens[0].position_length);
+    assert_eq!(parts.concat(), "関西国際空港");
+}
@@ -0,0 +1,46 @@
+extern crate mecab;
+
+mod common;
+
+use mecab::{Path, MECAB_NBEST};
+
+const SENTENCE: &str = "すもももももももものうち";
+
+fn assert_distinct(paths: &[Path]) {
+    assert!(paths.len() > 1, "expected several paths, got {}", paths.len());
+    for pair in paths.windows(2) {
+        assert_ne!(pair[0], pair[1]);
+        assert!(pair[0].cost <= pair[1].cost);
+    }
+}
+
+#[test]
+fn consecutive_paths_differ() {
+    let model = match common::model() {
+        Some(model) => model,
+        None => return,
+    };
+    let tagger = model.create_tagger();
+    let paths: Vec<Path> = tagger.nbest(SENTENCE, 5).collect();
+    assert_distinct(&paths);
+
+    let mut lattice = model.create_lattice();
+    lattice.set_sentence(SENTENCE);
+    assert!(tagger.parse(&lattice));
+    assert_eq!(paths[0].tokens, lattice.tokens());
+}
+
+#[test]
+fn nbest_lattice_keeps_the_sentence() {
+    let model = match common::model() {
+        Some(model) => model,
+        None => return,
+    };
+    let tagger = model.create_tagger();
+    let mut lattice = model.create_lattice();
+    lattice.set_sentence(SENTENCE);
+
+    let paths: Vec<Path> = tagger.nbest_lattice(&mut lattice, 5).collect();
+    assert_distinct(&paths);
+    assert!(lattice.has_request_type(MECAB_NBEST));
+}
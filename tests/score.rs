@@ -0,0 +1,40 @@
+extern crate mecab;
+
+mod common;
+
+use mecab::Segment;
+
+fn surfaces(tokens: &[mecab::Token]) -> Vec<&str> {
+    tokens.iter().map(|t| t.surface.as_str()).collect()
+}
+
+#[test]
+fn best_path_scores_zero() {
+    let model = match common::model() {
+        Some(model) => model,
+        None => return,
+    };
+
+    let best = model.score_segmentation(&[Segment::new("すもも")]).unwrap();
+    assert_eq!(best.cost_delta, 0);
+    assert!(best.differences.is_empty());
+    assert_eq!(best.proposed, best.best);
+}
+
+#[test]
+fn different_segmentation_costs_more() {
+    let model = match common::model() {
+        Some(model) => model,
+        None => return,
+    };
+
+    let segments: Vec<Segment> = ["すも", "もも", "もも", "もも", "の", "うち"].iter().map(|&s| Segment::from(s)).collect();
+    let score = model.score_segmentation(&segments).unwrap();
+
+    assert_eq!(surfaces(&score.proposed.tokens), ["すも", "もも", "もも", "もも", "の", "うち"]);
+    assert_ne!(score.proposed.tokens, score.best.tokens);
+    assert!(score.cost_delta > 0);
+    assert_eq!(score.cost_delta, score.proposed.cost - score.best.cost);
+    assert!(!score.differences.is_empty());
+    assert_eq!(score.differences[0].proposed[0].surface, "すも");
+}
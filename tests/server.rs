@@ -0,0 +1,257 @@
+extern crate mecab;
+extern crate serde_json;
+
+mod common;
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+const SENTENCE: &str = "すもももももももものうち";
+
+struct Server {
+    child: Child,
+    addr: String,
+}
+
+impl Server {
+    fn start(args: &[&str]) -> Server {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_mecab-rs-server"))
+            .arg("--listen=127.0.0.1:0")
+            .args(args)
+            .args(common::args())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+
+        let mut line = String::new();
+        BufReader::new(child.stdout.take().unwrap()).read_line(&mut line).unwrap();
+        let addr = line.trim().trim_start_matches("listening on http://").to_owned();
+
+        Server { child, addr }
+    }
+
+    fn request(&self, method: &str, path: &str, body: &str) -> (u16, String) {
+        self.send(method, path, body.len(), body, Duration::from_secs(0))
+    }
+
+    // declares `length` body bytes but only sends `body`, then waits `pause` before closing the request
+    fn send(&self, method: &str, path: &str, length: usize, body: &str, pause: Duration) -> (u16, String) {
+        let stream = self.stall(method, path, length, body);
+        thread::sleep(pause);
+        stream.shutdown(Shutdown::Write).unwrap();
+        read_response(stream)
+    }
+
+    // declares `length` body bytes but only sends `body` and keeps the request open
+    fn stall(&self, method: &str, path: &str, length: usize, body: &str) -> TcpStream {
+        let mut stream = TcpStream::connect(&self.addr).unwrap();
+        write!(stream,
+               "{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nContent-Type: application/json\r\n\
+                Content-Length: {}\r\n\r\n{}",
+               method,
+               path,
+               self.addr,
+               length,
+               body)
+            .unwrap();
+        stream
+    }
+
+    fn post(&self, path: &str, body: &str) -> (u16, Value) {
+        let (status, body) = self.request("POST", path, body);
+        (status, serde_json::from_str(&body).unwrap())
+    }
+
+    // waits until the model finished loading, true if it loaded successfully
+    fn wait_ready(&self) -> bool {
+        let started = Instant::now();
+        loop {
+            let (status, body) = self.request("GET", "/ready", "");
+            if status == 200 {
+                return true;
+            }
+            if body.contains("failed") || started.elapsed() > Duration::from_secs(10) {
+                return false;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+    }
+}
+
+fn read_response(mut stream: TcpStream) -> (u16, String) {
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let status = response[9..12].parse().unwrap();
+    let body = response.split("\r\n\r\n").nth(1).unwrap_or("").to_owned();
+    (status, body)
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[test]
+fn health() {
+    let server = Server::start(&[]);
+    let (status, body) = server.request("GET", "/health", "");
+    assert_eq!(status, 200);
+    assert!(body.contains("\"ok\""));
+}
+
+#[test]
+fn unknown_path_and_method() {
+    let server = Server::start(&[]);
+    assert_eq!(server.request("GET", "/nope", "").0, 404);
+    assert_eq!(server.request("GET", "/tokenize", "").0, 405);
+}
+
+#[test]
+fn body_size_limit() {
+    let server = Server::start(&["--max-body-size=16"]);
+    let (status, _) = server.request("POST", "/tokenize", r#"{"text": "すもももももももものうち"}"#);
+    assert_eq!(status, 413);
+}
+
+// the server for tests that need a dictionary, `None` after printing a notice without one
+fn ready_server() -> Option<Server> {
+    common::model()?;
+    let server = Server::start(&[]);
+    assert!(server.wait_ready());
+    Some(server)
+}
+
+fn surfaces(tokens: &Value) -> Vec<&str> {
+    tokens.as_array().unwrap().iter().map(|t| t["surface"].as_str().unwrap()).collect()
+}
+
+#[test]
+fn not_ready_without_dictionary() {
+    if common::model().is_some() {
+        return;
+    }
+
+    let server = Server::start(&[]);
+    assert!(!server.wait_ready());
+    assert_eq!(server.request("POST", "/tokenize", r#"{"text": "すもも"}"#).0, 503);
+    assert_eq!(server.request("GET", "/dictionary-info", "").0, 503);
+}
+
+#[test]
+fn tokenize() {
+    let server = match ready_server() {
+        Some(server) => server,
+        None => return,
+    };
+
+    let (status, body) = server.post("/tokenize", &format!(r#"{{"text": "{}"}}"#, SENTENCE));
+    assert_eq!(status, 200);
+    assert_eq!(body["sentence"], SENTENCE);
+    let surfaces = surfaces(&body["tokens"]);
+    assert_eq!(surfaces[0], "すもも");
+    assert_eq!(surfaces.concat(), SENTENCE);
+}
+
+#[test]
+fn nbest() {
+    let server = match ready_server() {
+        Some(server) => server,
+        None => return,
+    };
+
+    let (_, best) = server.post("/tokenize", &format!(r#"{{"text": "{}"}}"#, SENTENCE));
+    let (status, body) = server.post("/nbest", &format!(r#"{{"text": "{}", "n": 3}}"#, SENTENCE));
+    assert_eq!(status, 200);
+    let paths = body["paths"].as_array().unwrap();
+    assert_eq!(paths.len(), 3);
+    assert_eq!(paths[0]["tokens"], best["tokens"]);
+    for pair in paths.windows(2) {
+        assert_ne!(pair[0]["tokens"], pair[1]["tokens"]);
+        assert!(pair[0]["cost"].as_i64() <= pair[1]["cost"].as_i64());
+    }
+
+    // the pooled tagger is back to one-best afterwards
+    let (_, again) = server.post("/tokenize", &format!(r#"{{"text": "{}"}}"#, SENTENCE));
+    assert_eq!(again["tokens"], best["tokens"]);
+}
+
+#[test]
+fn lattice() {
+    let server = match ready_server() {
+        Some(server) => server,
+        None => return,
+    };
+
+    let (status, body) = server.post("/lattice", r#"{"text": "すもも"}"#);
+    assert_eq!(status, 200);
+    assert_eq!(body["sentence"], "すもも");
+    let nodes = body["nodes"].as_array().unwrap();
+    assert!(nodes.iter().any(|n| n["surface"] == "すもも" && n["is_best"] == true));
+    assert!(!body["edges"].as_array().unwrap().is_empty());
+    assert!(!body["best_path"].as_array().unwrap().is_empty());
+}
+
+#[test]
+fn dictionary_info() {
+    let server = match ready_server() {
+        Some(server) => server,
+        None => return,
+    };
+
+    let (status, body) = server.request("GET", "/dictionary-info", "");
+    assert_eq!(status, 200);
+    let body: Value = serde_json::from_str(&body).unwrap();
+    let dictionaries = body["dictionaries"].as_array().unwrap();
+    let sys = dictionaries.iter().find(|d| d["type"] == mecab::MECAB_SYS_DIC).unwrap();
+    assert!(sys["filename"].as_str().unwrap().ends_with("sys.dic"));
+    assert!(sys["size"].as_u64().unwrap() > 0);
+    assert!(sys["lsize"].as_u64().unwrap() > 0);
+}
+
+#[test]
+fn body_read_timeout() {
+    let server = Server::start(&["--timeout=100"]);
+    // bodies up to 1024 bytes are buffered by tiny_http before the request is handed over
+    let (status, _) = server.send("POST", "/tokenize", 2048, r#"{"text": "#, Duration::from_millis(500));
+    assert_eq!(status, 408);
+}
+
+#[test]
+fn stalled_body() {
+    let server = Server::start(&["--timeout=300", "--max-readers=1"]);
+    let started = Instant::now();
+    let stream = server.stall("POST", "/tokenize", 2048, r#"{"text": "#);
+    thread::sleep(Duration::from_millis(100));
+
+    // the only reader is taken by the stalled request
+    assert_eq!(server.request("POST", "/tokenize", "{").0, 503);
+
+    // the stalled request is answered without the client closing it
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    assert_eq!(read_response(stream).0, 408);
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert_eq!(server.request("POST", "/tokenize", "{").0, 400);
+}
+
+#[test]
+fn nbest_limits() {
+    let server = Server::start(&["--max-nbest=2"]);
+    server.wait_ready();
+    let (status, _) = server.request("POST", "/nbest", r#"{"text": "すもも", "n": 3}"#);
+    assert_eq!(status, 400);
+}
+
+#[test]
+fn invalid_json() {
+    let server = Server::start(&[]);
+    server.wait_ready();
+    assert_eq!(server.request("POST", "/tokenize", "{").0, 400);
+}
@@ -0,0 +1,17 @@
+extern crate mecab;
+
+mod common;
+
+#[test]
+fn parse_to_tokens_replaces_invalid_utf8() {
+    let model = match common::model() {
+        Some(model) => model,
+        None => return,
+    };
+
+    let mut tagger = model.create_tagger();
+    let tokens = tagger.parse_to_tokens(b"\xffabc".to_vec());
+    let text: String = tokens.iter().map(|t| t.surface.as_str()).collect();
+    assert_eq!(text, "\u{fffd}abc");
+    assert_eq!(tokens.last().unwrap().span.end, "\u{fffd}abc".len());
+}
@@ -0,0 +1,62 @@
+extern crate mecab;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use mecab::{SystemEval, TestGen, ToolError};
+
+// runs without the test harness, the tools are started in a child process of this executable
+// which has to call `tool_entry_point` before anything else
+fn main() {
+    mecab::tool_entry_point();
+
+    let dir = env::temp_dir().join(format!("mecab-tools-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    test_gen(&dir);
+    system_eval(&dir);
+    missing_input(&dir);
+    fs::remove_dir_all(&dir).unwrap();
+    println!("tools: ok");
+}
+
+fn corpus(dir: &Path, name: &str, words: &[(&str, &str)]) -> PathBuf {
+    let path = dir.join(name);
+    let mut text = String::new();
+    for &(surface, feature) in words {
+        text.push_str(&format!("{}\t{}\n", surface, feature));
+    }
+    text.push_str("EOS\n");
+    fs::write(&path, text).unwrap();
+    path
+}
+
+const NOUN: &str = "名詞,一般,*,*,*,*,*";
+const PARTICLE: &str = "助詞,係助詞,*,*,*,*,*";
+
+fn test_gen(dir: &Path) {
+    let input = corpus(dir, "test-gen.txt", &[("すもも", NOUN), ("も", PARTICLE), ("もも", NOUN)]);
+    let output = TestGen::new(&input).run().unwrap();
+    assert_eq!(output.status, 0);
+    assert_eq!(output.stdout, "すももももも\n");
+}
+
+fn system_eval(dir: &Path) {
+    let answer = corpus(dir, "answer.txt", &[("すもも", NOUN), ("も", PARTICLE), ("もも", NOUN)]);
+    let system = corpus(dir, "system.txt", &[("すもも", NOUN), ("も", NOUN), ("もも", NOUN)]);
+    let evaluation = SystemEval::new(&system, &answer).levels(&[0, -1]).run().unwrap();
+
+    // level 0 only compares the surfaces
+    let levels: Vec<_> = evaluation.levels.iter().map(|l| (l.level, l.correct, l.system, l.answer)).collect();
+    assert_eq!(levels, [(Some(0), 3, 3, 3), (None, 2, 3, 3)]);
+    assert_eq!(evaluation.levels[0].precision, 100.0);
+    assert!((evaluation.levels[1].recall - 200.0 / 3.0).abs() < 0.01);
+}
+
+fn missing_input(dir: &Path) {
+    match TestGen::new(dir.join("missing.txt")).run() {
+        Err(ToolError::NotFound(path)) => assert_eq!(path, dir.join("missing.txt")),
+        other => panic!("unexpected {:?}", other),
+    }
+}
@@ -0,0 +1,67 @@
+extern crate mecab;
+extern crate serde_json;
+
+mod common;
+
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+
+use serde_json::Value;
+
+// sends one request per line to `mecab-rs worker` and returns the responses
+fn run(requests: &[&str]) -> Vec<Value> {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_mecab-rs"))
+        .arg("worker")
+        .args(common::args())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    {
+        let mut stdin = child.stdin.take().unwrap();
+        for request in requests {
+            writeln!(stdin, "{}", request).unwrap();
+        }
+    }
+    let responses = BufReader::new(child.stdout.take().unwrap())
+        .lines()
+        .map(|line| serde_json::from_str(&line.unwrap()).unwrap())
+        .collect();
+    assert!(child.wait().unwrap().success());
+    responses
+}
+
+fn surfaces(tokens: &Value) -> Vec<&str> {
+    tokens.as_array().unwrap().iter().map(|t| t["surface"].as_str().unwrap()).collect()
+}
+
+#[test]
+fn constraints_change_the_segmentation() {
+    if common::model().is_none() {
+        return;
+    }
+
+    let constrained = r#"{"id": 2, "text": "すもももももももものうち", "constraints": [{"kind": "token", "begin": 0, "end": 2}]}"#;
+    let responses = run(&[r#"{"id": 1, "text": "すもももももももものうち"}"#, constrained]);
+    let free = surfaces(&responses[0]["tokens"]);
+    let constrained = surfaces(&responses[1]["tokens"]);
+
+    assert_eq!(free[0], "すもも");
+    assert_eq!(constrained[0], "すも");
+    assert_eq!(constrained.concat(), "すもももももももものうち");
+}
+
+#[test]
+fn nbest_paths_differ() {
+    if common::model().is_none() {
+        return;
+    }
+
+    let responses = run(&[r#"{"id": 1, "text": "すもももももももものうち", "nbest": 3}"#]);
+    let paths = responses[0]["paths"].as_array().unwrap();
+    assert_eq!(paths.len(), 3);
+    for pair in paths.windows(2) {
+        assert_ne!(pair[0]["tokens"], pair[1]["tokens"]);
+    }
+}